@@ -0,0 +1,82 @@
+#!/usr/bin/env python3
+"""Generates the small ELF core dumps in ci/testdata.
+
+The cores only hold the headers and notes that describe the memory layout of
+a process running /usr/bin/sleep, and none of its memory contents.
+"""
+
+import os
+import struct
+
+PT_LOAD = 1
+PT_NOTE = 4
+PF_X, PF_W, PF_R = 1, 2, 4
+NT_PRPSINFO = 3
+NT_FILE = 0x46494C45
+PAGE_SIZE = 0x1000
+
+
+def note(name, n_type, desc):
+    name = name + b"\0"
+    pad = lambda b: b + b"\0" * (-len(b) % 4)
+    return struct.pack("<III", len(name), len(desc), n_type) + pad(name) + pad(desc)
+
+
+def core(is_64, loads, files):
+    word = "Q" if is_64 else "I"
+    ehsize, phentsize = (64, 56) if is_64 else (52, 32)
+
+    nt_file = struct.pack("<" + word * 2, len(files), PAGE_SIZE)
+    for start, end, offset, _ in files:
+        nt_file += struct.pack("<" + word * 3, start, end, offset // PAGE_SIZE)
+    nt_file += b"".join(name + b"\0" for _, _, _, name in files)
+    notes = note(b"CORE", NT_PRPSINFO, b"\0" * (136 if is_64 else 124))
+    notes += note(b"CORE", NT_FILE, nt_file)
+
+    phnum = 1 + len(loads)
+    notes_offset = ehsize + phentsize * phnum
+    headers = [(PT_NOTE, 0, notes_offset, 0, len(notes), 0, 4)]
+    headers += [(PT_LOAD, flags, 0, start, 0, end - start, PAGE_SIZE) for start, end, flags in loads]
+
+    if is_64:
+        ident = b"\x7fELF" + bytes([2, 1, 1, 0]) + b"\0" * 8
+        out = ident + struct.pack("<HHIQQQIHHHHHH", 4, 62, 1, 0, ehsize, 0, 0, ehsize, phentsize, phnum, 0, 0, 0)
+        for p_type, flags, offset, vaddr, filesz, memsz, align in headers:
+            out += struct.pack("<IIQQQQQQ", p_type, flags, offset, vaddr, 0, filesz, memsz, align)
+    else:
+        ident = b"\x7fELF" + bytes([1, 1, 1, 0]) + b"\0" * 8
+        out = ident + struct.pack("<HHIIIIIHHHHHH", 4, 3, 1, 0, ehsize, 0, 0, ehsize, phentsize, phnum, 0, 0, 0)
+        for p_type, flags, offset, vaddr, filesz, memsz, align in headers:
+            out += struct.pack("<IIIIIIII", p_type, offset, vaddr, 0, filesz, memsz, flags, align)
+    return out + notes
+
+
+def main():
+    testdata = os.path.join(os.path.dirname(__file__), "..", "testdata")
+    for is_64, base, lib, stack in [
+        (True, 0x55D0C1E00000, 0x7F0B4F000000, 0x7FFC8A3C1000),
+        (False, 0x56555000, 0xF7F00000, 0xFFDDE000),
+    ]:
+        loads = [
+            (base, base + 0x2000, PF_R),
+            (base + 0x2000, base + 0x6000, PF_R | PF_X),
+            (base + 0x8000, base + 0x9000, PF_R | PF_W),
+            (base + 0x9000, base + 0x2A000, PF_R | PF_W),
+            (lib, lib + 0x1000, PF_R),
+            (lib + 0x1000, lib + 0x3000, PF_R | PF_X),
+            (stack, stack + 0x21000, PF_R | PF_W),
+        ]
+        files = [
+            (base, base + 0x2000, 0, b"/usr/bin/sleep"),
+            (base + 0x2000, base + 0x6000, 0x2000, b"/usr/bin/sleep"),
+            (base + 0x8000, base + 0x9000, 0x7000, b"/usr/bin/sleep"),
+            (lib, lib + 0x1000, 0, b"/tmp/lib gone.so (deleted)"),
+            (lib + 0x1000, lib + 0x3000, 0x1000, b"/tmp/lib gone.so (deleted)"),
+        ]
+        name = "core64.elf" if is_64 else "core32.elf"
+        with open(os.path.join(testdata, name), "wb") as f:
+            f.write(core(is_64, loads, files))
+
+
+if __name__ == "__main__":
+    main()
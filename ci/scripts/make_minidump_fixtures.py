@@ -0,0 +1,117 @@
+#!/usr/bin/env python3
+"""Generates the small minidumps in ci/testdata.
+
+The minidumps only hold the streams that describe the memory layout of a
+process, and none of its memory contents or thread state.
+"""
+
+import os
+import struct
+
+MODULE_LIST_STREAM = 4
+MEMORY_INFO_LIST_STREAM = 16
+MD_LINUX_MAPS = 0x47670009
+
+MEM_COMMIT = 0x1000
+MEM_RESERVE = 0x2000
+MEM_FREE = 0x10000
+MEM_PRIVATE = 0x20000
+MEM_MAPPED = 0x40000
+MEM_IMAGE = 0x1000000
+
+PAGE_NOACCESS = 0x01
+PAGE_READONLY = 0x02
+PAGE_READWRITE = 0x04
+PAGE_WRITECOPY = 0x08
+PAGE_EXECUTE_READ = 0x20
+PAGE_GUARD = 0x100
+
+
+def minidump(streams):
+    """Lays out a header, the stream directory and then each stream, where
+    streams are (type, build) pairs and build(rva) returns the stream bytes,
+    so that streams can point at data stored after them"""
+    header_size = 32
+    directory = b""
+    body = b""
+    rva = header_size + 12 * len(streams)
+    for stream_type, build in streams:
+        data = build(rva)
+        directory += struct.pack("<III", stream_type, len(data), rva)
+        body += data
+        rva += len(data)
+    header = struct.pack("<IIIIIIQ", 0x504D444D, 0xA793, len(streams), header_size, 0, 0, 0)
+    return header + directory + body
+
+
+def module_list(modules):
+    def build(rva):
+        names = b""
+        entries = b""
+        names_rva = rva + 4 + 108 * len(modules)
+        for base, size, name in modules:
+            encoded = name.encode("utf-16-le")
+            name_rva = names_rva + len(names)
+            names += struct.pack("<I", len(encoded)) + encoded + b"\0\0"
+            entries += struct.pack("<QIIII", base, size, 0, 0, name_rva)
+            entries += b"\0" * (108 - 24)
+        return struct.pack("<I", len(modules)) + entries + names
+
+    return (MODULE_LIST_STREAM, build)
+
+
+def memory_info_list(regions):
+    def build(rva):
+        out = struct.pack("<IIQ", 16, 48, len(regions))
+        for base, size, state, protect, kind in regions:
+            out += struct.pack("<QQIIQIIII", base, base, protect, 0, size, state, protect, kind, 0)
+        return out
+
+    return (MEMORY_INFO_LIST_STREAM, build)
+
+
+def linux_maps(text):
+    return (MD_LINUX_MAPS, lambda rva: text.encode())
+
+
+def main():
+    testdata = os.path.join(os.path.dirname(__file__), "..", "testdata")
+    modules = [
+        (0x7FF6A0000000, 0x5000, "C:\\app\\app.exe"),
+        (0x7FFB10000000, 0x2000, "C:\\Windows\\System32\\kernel32.dll"),
+    ]
+    fixtures = {
+        "minidump_windows.dmp": [
+            memory_info_list(
+                [
+                    (0x10000, 0x10000, MEM_FREE, PAGE_NOACCESS, 0),
+                    (0x20000, 0x1000, MEM_COMMIT, PAGE_READWRITE, MEM_MAPPED),
+                    (0x30000, 0x3000, MEM_RESERVE, PAGE_NOACCESS, MEM_PRIVATE),
+                    (0x40000, 0x2000, MEM_COMMIT, PAGE_READWRITE | PAGE_GUARD, MEM_PRIVATE),
+                    (0x7FF6A0000000, 0x1000, MEM_COMMIT, PAGE_READONLY, MEM_IMAGE),
+                    (0x7FF6A0001000, 0x2000, MEM_COMMIT, PAGE_EXECUTE_READ, MEM_IMAGE),
+                    (0x7FF6A0003000, 0x2000, MEM_COMMIT, PAGE_WRITECOPY, MEM_IMAGE),
+                    (0x7FFB10000000, 0x2000, MEM_COMMIT, PAGE_EXECUTE_READ, MEM_IMAGE),
+                ]
+            ),
+            module_list(modules),
+        ],
+        "minidump_linux.dmp": [
+            module_list([(0x55D0C1E00000, 0x6000, "/usr/bin/sleep")]),
+            linux_maps(
+                "55d0c1e00000-55d0c1e02000 r--p 00000000 fd:01 1835042                    /usr/bin/sleep\n"
+                "55d0c1e02000-55d0c1e06000 r-xp 00002000 fd:01 1835042                    /usr/bin/sleep\n"
+                "55d0c2f1a000-55d0c2f3b000 rw-p 00000000 00:00 0                          [heap]\n"
+                "7f0b4d000000-7f0b4d800000 rw-s 00000000 00:01 4096                       /memfd:shm (deleted)\n"
+                "7ffc8a3c1000-7ffc8a3e2000 rw-p 00000000 00:00 0                          [stack]\n"
+            ),
+        ],
+        "minidump_modules.dmp": [module_list(modules)],
+    }
+    for name, streams in fixtures.items():
+        with open(os.path.join(testdata, name), "wb") as f:
+            f.write(minidump(streams))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,511 @@
+//! A minimal reader for the parts of little-endian ELF files that this crate
+//! needs: the file header, the program and section headers, notes and symbol
+//! tables.
+
+use std;
+use std::io::{Read, Seek, SeekFrom};
+
+pub const ET_CORE: u16 = 4;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_NOTE: u32 = 4;
+
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+pub const NT_GNU_BUILD_ID: u32 = 3;
+
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_DYNSYM: u32 = 11;
+
+const SHN_UNDEF: u16 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_GNU_IFUNC: u8 = 10;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+
+/// The largest table or segment that will be read into memory, so that a
+/// corrupt header can't cause a huge allocation
+const MAX_READ: u64 = 0x10000000;
+
+pub fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// The fields of the ELF file header that are used here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHeader {
+    pub is_64: bool,
+    pub e_type: u16,
+    pub e_machine: u16,
+    pub e_phoff: u64,
+    pub e_phentsize: u16,
+    pub e_phnum: u16,
+    pub e_shoff: u64,
+    pub e_shentsize: u16,
+    pub e_shnum: u16,
+}
+
+/// An entry from the program header table of an ELF file, which describes
+/// how a segment of the file is loaded into memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProgramHeader {
+    /// The kind of segment, such as `PT_LOAD` (1) for loaded segments
+    pub p_type: u32,
+    /// The `PF_X` (1), `PF_W` (2) and `PF_R` (4) permission bits
+    pub p_flags: u32,
+    /// The offset of the segment in the file
+    pub p_offset: u64,
+    /// The virtual address of the segment, before the load bias is added
+    pub p_vaddr: u64,
+    /// The number of bytes of the segment that are stored in the file
+    pub p_filesz: u64,
+    /// The size of the segment in memory, which is larger than `p_filesz`
+    /// for zero-filled data such as `.bss`
+    pub p_memsz: u64,
+    pub p_align: u64,
+}
+
+/// The fields of a section header that are used here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionHeader {
+    pub sh_type: u32,
+    pub sh_offset: u64,
+    pub sh_size: u64,
+    /// For symbol tables, the index of the section holding their names
+    pub sh_link: u32,
+}
+
+/// An entry from a symbol table, with its name still an offset into the
+/// linked string table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolEntry {
+    pub st_name: u32,
+    pub st_info: u8,
+    pub st_shndx: u16,
+    pub st_value: u64,
+    pub st_size: u64,
+}
+
+impl SymbolEntry {
+    /// Returns whether the symbol is a function or data object that is
+    /// defined in this file, rather than a section, file name, thread local or
+    /// an import from another file
+    pub fn is_defined_code_or_data(&self) -> bool {
+        match self.st_info & 0xf {
+            STT_OBJECT | STT_FUNC | STT_GNU_IFUNC => self.st_shndx != SHN_UNDEF,
+            _ => false,
+        }
+    }
+}
+
+/// A single entry from a note segment or section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Note<'a> {
+    /// The note's owner, without the nul terminator
+    pub name: &'a [u8],
+    pub n_type: u32,
+    pub desc: &'a [u8],
+}
+
+/// Reads the headers and segments of an ELF file
+pub struct ElfReader<R> {
+    reader: R,
+    header: FileHeader,
+}
+
+impl<R: Read + Seek> ElfReader<R> {
+    /// Reads the file header, failing if `reader` doesn't hold a 32 or 64 bit
+    /// little-endian ELF file
+    pub fn new(mut reader: R) -> std::io::Result<ElfReader<R>> {
+        let mut ident = [0u8; 64];
+        reader.seek(SeekFrom::Start(0))?;
+        let len = read_up_to(&mut reader, &mut ident)?;
+        if len < 16 || &ident[..4] != ELF_MAGIC {
+            return Err(invalid_data("not an ELF file"));
+        }
+        let is_64 = match ident[4] {
+            ELFCLASS32 => false,
+            ELFCLASS64 => true,
+            _ => return Err(invalid_data("unknown ELF class")),
+        };
+        if ident[5] != ELFDATA2LSB {
+            return Err(invalid_data("big-endian ELF files aren't supported"));
+        }
+        let header = if is_64 {
+            if len < 64 {
+                return Err(invalid_data("truncated ELF header"));
+            }
+            FileHeader {
+                is_64,
+                e_type: read_u16(&ident, 16),
+                e_machine: read_u16(&ident, 18),
+                e_phoff: read_u64(&ident, 32),
+                e_phentsize: read_u16(&ident, 54),
+                e_phnum: read_u16(&ident, 56),
+                e_shoff: read_u64(&ident, 40),
+                e_shentsize: read_u16(&ident, 58),
+                e_shnum: read_u16(&ident, 60),
+            }
+        } else {
+            if len < 52 {
+                return Err(invalid_data("truncated ELF header"));
+            }
+            FileHeader {
+                is_64,
+                e_type: read_u16(&ident, 16),
+                e_machine: read_u16(&ident, 18),
+                e_phoff: read_u32(&ident, 28) as u64,
+                e_phentsize: read_u16(&ident, 42),
+                e_phnum: read_u16(&ident, 44),
+                e_shoff: read_u32(&ident, 32) as u64,
+                e_shentsize: read_u16(&ident, 46),
+                e_shnum: read_u16(&ident, 48),
+            }
+        };
+        Ok(ElfReader { reader, header })
+    }
+
+    pub fn header(&self) -> &FileHeader {
+        &self.header
+    }
+
+    /// Reads the program header table
+    pub fn program_headers(&mut self) -> std::io::Result<Vec<ProgramHeader>> {
+        let is_64 = self.header.is_64;
+        let entry_size = self.header.e_phentsize as usize;
+        if self.header.e_phnum > 0 && entry_size < if is_64 { 56 } else { 32 } {
+            return Err(invalid_data("invalid program header size"));
+        }
+        let table = self.read_at(
+            self.header.e_phoff,
+            entry_size as u64 * self.header.e_phnum as u64,
+        )?;
+        Ok(table
+            .chunks_exact(entry_size.max(1))
+            .map(|entry| {
+                if is_64 {
+                    ProgramHeader {
+                        p_type: read_u32(entry, 0),
+                        p_flags: read_u32(entry, 4),
+                        p_offset: read_u64(entry, 8),
+                        p_vaddr: read_u64(entry, 16),
+                        p_filesz: read_u64(entry, 32),
+                        p_memsz: read_u64(entry, 40),
+                        p_align: read_u64(entry, 48),
+                    }
+                } else {
+                    ProgramHeader {
+                        p_type: read_u32(entry, 0),
+                        p_offset: read_u32(entry, 4) as u64,
+                        p_vaddr: read_u32(entry, 8) as u64,
+                        p_filesz: read_u32(entry, 16) as u64,
+                        p_memsz: read_u32(entry, 20) as u64,
+                        p_flags: read_u32(entry, 24),
+                        p_align: read_u32(entry, 28) as u64,
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Reads the section header table, which is empty for core dumps and
+    /// files that have had their section headers stripped
+    pub fn section_headers(&mut self) -> std::io::Result<Vec<SectionHeader>> {
+        let is_64 = self.header.is_64;
+        let entry_size = self.header.e_shentsize as usize;
+        if self.header.e_shoff == 0 || self.header.e_shnum == 0 {
+            return Ok(Vec::new());
+        }
+        if entry_size < if is_64 { 64 } else { 40 } {
+            return Err(invalid_data("invalid section header size"));
+        }
+        let table = self.read_at(
+            self.header.e_shoff,
+            entry_size as u64 * self.header.e_shnum as u64,
+        )?;
+        Ok(table
+            .chunks_exact(entry_size)
+            .map(|entry| {
+                if is_64 {
+                    SectionHeader {
+                        sh_type: read_u32(entry, 4),
+                        sh_offset: read_u64(entry, 24),
+                        sh_size: read_u64(entry, 32),
+                        sh_link: read_u32(entry, 40),
+                    }
+                } else {
+                    SectionHeader {
+                        sh_type: read_u32(entry, 4),
+                        sh_offset: read_u32(entry, 16) as u64,
+                        sh_size: read_u32(entry, 20) as u64,
+                        sh_link: read_u32(entry, 24),
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Reads the contents of a section
+    pub fn read_section(&mut self, header: &SectionHeader) -> std::io::Result<Vec<u8>> {
+        self.read_at(header.sh_offset, header.sh_size)
+    }
+
+    /// Reads the contents of a segment that are stored in the file
+    pub fn read_segment(&mut self, header: &ProgramHeader) -> std::io::Result<Vec<u8>> {
+        self.read_at(header.p_offset, header.p_filesz)
+    }
+
+    /// Reads `len` bytes starting at `offset`, failing if the file is shorter
+    pub fn read_at(&mut self, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+        if len > MAX_READ {
+            return Err(invalid_data("ELF table is too large"));
+        }
+        let mut buffer = Vec::new();
+        self.reader.seek(SeekFrom::Start(offset))?;
+        (&mut self.reader).take(len).read_to_end(&mut buffer)?;
+        if (buffer.len() as u64) < len {
+            return Err(invalid_data("ELF file is truncated"));
+        }
+        Ok(buffer)
+    }
+}
+
+/// Works out the load bias of an ELF file (the difference between the
+/// addresses it was loaded at and the virtual addresses in its program
+/// headers) from one of its mappings, which maps `offset` in the file at
+/// `start`. Segments can share a page of the file, so the mapping with the
+/// lowest offset gives the most reliable answer. Returns `None` if no
+/// `PT_LOAD` segment covers the offset.
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+pub fn load_bias(
+    headers: &[ProgramHeader],
+    start: u64,
+    offset: u64,
+    page_size: u64,
+) -> Option<u64> {
+    let segment = headers.iter().find(|h| {
+        // The kernel maps segments from the start of the page that holds them
+        let first = h.p_offset - (h.p_vaddr % page_size).min(h.p_offset);
+        h.p_type == PT_LOAD && first <= offset && offset < h.p_offset.saturating_add(h.p_filesz)
+    })?;
+    // The mapping puts file offset `offset` at `start`, and the segment puts
+    // `p_offset` at `p_vaddr + bias`
+    Some(
+        start
+            .wrapping_add(segment.p_offset)
+            .wrapping_sub(segment.p_vaddr)
+            .wrapping_sub(offset),
+    )
+}
+
+/// Finds the `NT_GNU_BUILD_ID` note in the note segments of an ELF file,
+/// reading each segment from the position that `locate` returns for its
+/// program header
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+pub fn build_id<R, F>(
+    elf: &mut ElfReader<R>,
+    headers: &[ProgramHeader],
+    locate: F,
+) -> Option<Vec<u8>>
+where
+    R: Read + Seek,
+    F: Fn(&ProgramHeader) -> u64,
+{
+    for header in headers.iter().filter(|h| h.p_type == PT_NOTE) {
+        let data = match elf.read_at(locate(header), header.p_filesz) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+        let found = notes(&data, header.p_align)
+            .find(|note| note.name == b"GNU" && note.n_type == NT_GNU_BUILD_ID);
+        if let Some(note) = found {
+            return Some(note.desc.to_vec());
+        }
+    }
+    None
+}
+
+/// Parses the entries of a symbol table section
+pub fn symbols(data: &[u8], is_64: bool) -> Vec<SymbolEntry> {
+    let entry_size = if is_64 { 24 } else { 16 };
+    data.chunks_exact(entry_size)
+        .map(|entry| {
+            if is_64 {
+                SymbolEntry {
+                    st_name: read_u32(entry, 0),
+                    st_info: entry[4],
+                    st_shndx: read_u16(entry, 6),
+                    st_value: read_u64(entry, 8),
+                    st_size: read_u64(entry, 16),
+                }
+            } else {
+                SymbolEntry {
+                    st_name: read_u32(entry, 0),
+                    st_value: read_u32(entry, 4) as u64,
+                    st_size: read_u32(entry, 8) as u64,
+                    st_info: entry[12],
+                    st_shndx: read_u16(entry, 14),
+                }
+            }
+        })
+        .collect()
+}
+
+/// Looks up a nul terminated string in a string table section
+pub fn string_at(strings: &[u8], offset: u32) -> Option<&[u8]> {
+    let rest = strings.get(offset as usize..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    Some(&rest[..len])
+}
+
+/// Iterates over the notes in the contents of a note segment. Notes that run
+/// past the end of the data end the iteration.
+pub fn notes(data: &[u8], align: u64) -> NoteIter<'_> {
+    NoteIter {
+        data,
+        align: if align == 8 { 8 } else { 4 },
+    }
+}
+
+pub struct NoteIter<'a> {
+    data: &'a [u8],
+    align: usize,
+}
+
+impl<'a> Iterator for NoteIter<'a> {
+    type Item = Note<'a>;
+
+    fn next(&mut self) -> Option<Note<'a>> {
+        if self.data.len() < 12 {
+            return None;
+        }
+        let name_size = read_u32(self.data, 0) as usize;
+        let desc_size = read_u32(self.data, 4) as usize;
+        let n_type = read_u32(self.data, 8);
+        let desc_start = align_up(12usize.checked_add(name_size)?, self.align)?;
+        let desc_end = desc_start.checked_add(desc_size)?;
+        if desc_end > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let name = &self.data[12..12 + name_size];
+        let note = Note {
+            name: name.strip_suffix(b"\0").unwrap_or(name),
+            n_type,
+            desc: &self.data[desc_start..desc_end],
+        };
+        let next = align_up(desc_end, self.align).unwrap_or(desc_end);
+        self.data = &self.data[next.min(self.data.len())..];
+        Some(note)
+    }
+}
+
+fn align_up(value: usize, align: usize) -> Option<usize> {
+    Some(value.checked_add(align - 1)? & !(align - 1))
+}
+
+/// Reads into `buffer` until it is full or the reader is exhausted, returning
+/// the number of bytes read
+fn read_up_to<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+pub fn read_u16(data: &[u8], offset: usize) -> u16 {
+    let mut bytes = [0u8; 2];
+    bytes.copy_from_slice(&data[offset..offset + 2]);
+    u16::from_le_bytes(bytes)
+}
+
+pub fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+pub fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Reads a `long`, which is 4 or 8 bytes depending on the ELF class
+pub fn read_word(data: &[u8], offset: usize, is_64: bool) -> u64 {
+    if is_64 {
+        read_u64(data, offset)
+    } else {
+        read_u32(data, offset) as u64
+    }
+}
+
+#[test]
+fn test_load_bias() {
+    let load = |p_offset, p_vaddr, p_filesz| ProgramHeader {
+        p_type: PT_LOAD,
+        p_flags: PF_R,
+        p_offset,
+        p_vaddr,
+        p_filesz,
+        p_memsz: p_filesz,
+        p_align: 0x1000,
+    };
+    // A position independent executable, whose data segment starts part way
+    // through a page
+    let pie = [
+        load(0, 0, 0x1000),
+        load(0x1000, 0x1000, 0x2000),
+        load(0x3d10, 0x4d10, 0x300),
+    ];
+    let base = 0x5555_0000_0000;
+    assert_eq!(load_bias(&pie, base, 0, 0x1000), Some(base));
+    assert_eq!(load_bias(&pie, base + 0x4000, 0x3000, 0x1000), Some(base));
+    assert_eq!(load_bias(&pie, base, 0x5000, 0x1000), None);
+
+    // A fixed position executable isn't moved
+    let fixed = [load(0, 0x40_0000, 0x1000), load(0x1000, 0x40_1000, 0x1000)];
+    assert_eq!(load_bias(&fixed, 0x40_1000, 0x1000, 0x1000), Some(0));
+}
+
+#[test]
+fn test_notes() {
+    let mut data = Vec::new();
+    // "GNU\0" with a 4 byte descriptor, then "CORE\0" which needs padding
+    data.extend_from_slice(&[4, 0, 0, 0, 4, 0, 0, 0, 3, 0, 0, 0]);
+    data.extend_from_slice(b"GNU\0\x01\x02\x03\x04");
+    data.extend_from_slice(&[5, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0]);
+    data.extend_from_slice(b"CORE\0\0\0\0\x05\x06\0\0");
+    // Truncated
+    data.extend_from_slice(&[5, 0, 0, 0, 200, 0, 0, 0, 1, 0, 0, 0]);
+
+    let notes: Vec<Note> = notes(&data, 4).collect();
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].name, b"GNU");
+    assert_eq!(notes[0].n_type, 3);
+    assert_eq!(notes[0].desc, &[1, 2, 3, 4]);
+    assert_eq!(notes[1].name, b"CORE");
+    assert_eq!(notes[1].desc, &[5, 6]);
+}
+
+#[test]
+fn test_not_elf() {
+    let err = ElfReader::new(std::io::Cursor::new(&b"#!/bin/sh\n"[..])).err();
+    assert_eq!(err.unwrap().kind(), std::io::ErrorKind::InvalidData);
+    let mut big_endian = b"\x7fELF\x02\x02\x01".to_vec();
+    big_endian.resize(64, 0);
+    assert!(ElfReader::new(std::io::Cursor::new(big_endian)).is_err());
+}
@@ -9,10 +9,12 @@ use std::iter::Iterator;
 use std::path::{Path, PathBuf};
 
 use MapRangeImpl;
+use Permissions;
 
 pub type Pid = pid_t;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[allow(dead_code)]
 pub struct MapRange {
     range_start: usize,
@@ -42,6 +44,11 @@ impl MapRangeImpl for MapRange {
     fn is_exec(&self) -> bool {
         self.protection & protection::VM_PROT_EXECUTE != 0
     }
+    /// PT_VM_ENTRY doesn't report whether an entry is shared, so every range
+    /// is reported as private
+    fn permissions(&self) -> Permissions {
+        Permissions::new(self.is_read(), self.is_write(), self.is_exec(), false)
+    }
 }
 
 impl From<ptrace::VmEntry> for MapRange {
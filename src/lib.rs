@@ -21,8 +21,40 @@
 //!    println!("Filename {:?} Address {} Size {}", map.filename(), map.start(), map.size());
 //! }
 //! ```
+//!
+//! # Serde
+//!
+//! With the `serde` feature enabled, every platform's `MapRange`,
+//! [`MinidumpRegion`](minidump/struct.MinidumpRegion.html) and the Linux
+//! snapshot types (such as [`MemoryUsage`](linux_maps/struct.MemoryUsage.html)
+//! and [`NumaMapping`](linux_maps/struct.NumaMapping.html)) implement
+//! `Serialize` and `Deserialize`. Fields are serialized under their Rust
+//! names, and the schema only changes in a semver breaking release.
+//! Addresses and sizes are integers, and paths are strings, so paths that
+//! aren't valid unicode fail to serialize.
+//!
+//! The `MapRange` fields on each platform are:
+//!
+//! * Linux: `range_start`, `range_end`, `offset`, `dev` (`{major, minor}`),
+//!   `perms` (`{read, write, exec, shared}`), `inode`, `pathname` (without
+//!   the ` (deleted)` marker), `deleted`, `memory_usage` (the byte counts from
+//!   smaps, or null), `vm_flags` (the smaps mnemonics as a space separated
+//!   string, or null) and `build_id` (the GNU build ID as a lowercase hex
+//!   string, or null).
+//! * macOS: `start`, `size`, `filename`, and the `protection`,
+//!   `max_protection`, `inheritance`, `shared`, `reserved`, `offset`,
+//!   `behavior` and `user_wired_count` fields of `vm_region_basic_info_64`.
+//! * Windows: `base_addr`, `base_size`, `pathname`, `read`, `write`, `exec`
+//!   and `shared`.
+//! * FreeBSD: `range_start`, `range_end`, `protection` (the `VM_PROT_*` bits),
+//!   `offset`, `vnode` and `pathname`.
+//!
+//! `MinidumpRegion` has `range_start`, `range_size`, `pathname` and `perms`.
 
 extern crate libc;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 
 #[cfg(target_os = "macos")]
 extern crate anyhow;
@@ -33,12 +65,22 @@ extern crate mach2;
 #[cfg(windows)]
 extern crate winapi;
 
+#[cfg(test)]
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
+mod elf;
+pub mod minidump;
+mod permissions;
+pub use permissions::Permissions;
+
 #[cfg(target_os = "macos")]
 pub mod mac_maps;
 #[cfg(target_os = "macos")]
 pub use mac_maps::{get_process_maps, MapRange, Pid};
 
-#[cfg(any(target_os = "linux", target_os = "android"))]
+// The Linux maps parser is available everywhere, to load maps files that were
+// captured on Linux
 pub mod linux_maps;
 #[cfg(any(target_os = "linux", target_os = "android"))]
 pub use linux_maps::{get_process_maps, MapRange, Pid};
@@ -70,41 +112,60 @@ trait MapRangeImpl {
     fn is_write(&self) -> bool;
     /// Returns whether this range contains readable memory
     fn is_read(&self) -> bool;
+    /// Returns the access permissions of this range
+    fn permissions(&self) -> Permissions;
 }
 
-impl MapRange {
-    /// Returns the size of this MapRange in bytes
-    #[inline]
-    pub fn size(&self) -> usize {
-        MapRangeImpl::size(self)
-    }
-    /// Returns the address this MapRange starts at
-    #[inline]
-    pub fn start(&self) -> usize {
-        MapRangeImpl::start(self)
-    }
-    /// Returns the filename of the loaded module
-    #[inline]
-    pub fn filename(&self) -> Option<&std::path::Path> {
-        MapRangeImpl::filename(self)
-    }
-    /// Returns whether this range contains executable code
-    #[inline]
-    pub fn is_exec(&self) -> bool {
-        MapRangeImpl::is_exec(self)
-    }
-    /// Returns whether this range contains writeable memory
-    #[inline]
-    pub fn is_write(&self) -> bool {
-        MapRangeImpl::is_write(self)
-    }
-    /// Returns whether this range contains readable memory
-    #[inline]
-    pub fn is_read(&self) -> bool {
-        MapRangeImpl::is_read(self)
-    }
+/// Provides the inherent methods for a MapRange type by forwarding them to
+/// its MapRangeImpl
+macro_rules! impl_map_range {
+    ($t:ty) => {
+        impl $t {
+            /// Returns the size of this MapRange in bytes
+            #[inline]
+            pub fn size(&self) -> usize {
+                MapRangeImpl::size(self)
+            }
+            /// Returns the address this MapRange starts at
+            #[inline]
+            pub fn start(&self) -> usize {
+                MapRangeImpl::start(self)
+            }
+            /// Returns the filename of the loaded module
+            #[inline]
+            pub fn filename(&self) -> Option<&std::path::Path> {
+                MapRangeImpl::filename(self)
+            }
+            /// Returns whether this range contains executable code
+            #[inline]
+            pub fn is_exec(&self) -> bool {
+                MapRangeImpl::is_exec(self)
+            }
+            /// Returns whether this range contains writeable memory
+            #[inline]
+            pub fn is_write(&self) -> bool {
+                MapRangeImpl::is_write(self)
+            }
+            /// Returns whether this range contains readable memory
+            #[inline]
+            pub fn is_read(&self) -> bool {
+                MapRangeImpl::is_read(self)
+            }
+            /// Returns the access permissions of this range, including whether it is
+            /// shared or private
+            #[inline]
+            pub fn permissions(&self) -> Permissions {
+                MapRangeImpl::permissions(self)
+            }
+        }
+    };
 }
 
+impl_map_range!(MapRange);
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+impl_map_range!(linux_maps::MapRange);
+impl_map_range!(minidump::MinidumpRegion);
+
 fn map_contain_addr(map: &MapRange, addr: usize) -> bool {
     let start = map.start();
     (addr >= start) && (addr < (start + map.size()))
@@ -158,7 +219,7 @@ mod tests {
 
     #[cfg(not(target_os = "freebsd"))]
     #[test]
-    fn test_map_from_test_binary_present() -> () {
+    fn test_map_from_test_binary_present() {
         let maps = get_process_maps(std::process::id() as Pid).unwrap();
 
         let region = maps.iter().find(|map| {
@@ -177,24 +238,23 @@ mod tests {
 
     #[cfg(not(target_os = "windows"))]
     #[test]
-    fn test_map_from_invoked_binary_present() -> () {
+    fn test_map_from_invoked_binary_present() {
         let path = test_process_path().unwrap();
         if !path.exists() {
             println!("Skipping test because the 'test' binary hasn't been built");
             return;
         }
 
+        let mut child = std::process::Command::new(&path)
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to execute test process");
+
         let mut have_expected_map = false;
         // The maps aren't populated immediately on Linux, so retry a few times if needed
         for _ in 1..10 {
-            let mut child = std::process::Command::new(&path)
-                .spawn()
-                .expect("failed to execute test process");
-
             let maps = get_process_maps(child.id() as Pid).unwrap();
 
-            child.kill().expect("failed to kill test process");
-
             let region = maps.iter().find(|map| {
                 if let Some(filename) = map.filename() {
                     filename.to_string_lossy().contains("/test")
@@ -211,6 +271,9 @@ mod tests {
             }
         }
 
+        child.kill().expect("failed to kill test process");
+        child.wait().expect("failed to wait on test process");
+
         assert!(
             have_expected_map,
             "We should have a map from the binary we invoked!"
@@ -0,0 +1,194 @@
+use std;
+use std::io::{Read, Seek};
+
+use super::MapRange;
+use elf::{ElfReader, ProgramHeader, PF_W, PF_X, PT_LOAD};
+
+/// Reads the program headers of an ELF file, for converting between the
+/// addresses in a mapping of the file and its ELF virtual addresses with
+/// [`MapRange::to_elf_vaddr`](struct.MapRange.html#method.to_elf_vaddr)
+pub fn read_program_headers<R: Read + Seek>(reader: R) -> std::io::Result<Vec<ProgramHeader>> {
+    ElfReader::new(reader)?.program_headers()
+}
+
+impl MapRange {
+    /// Converts an address in this region to the offset in the mapped file
+    /// that it holds. Returns `None` if the address is outside the region, or
+    /// if the region isn't a mapping of a file.
+    pub fn to_file_offset(&self, addr: usize) -> Option<usize> {
+        if self.pathname.is_none() || addr < self.range_start || addr >= self.range_end {
+            return None;
+        }
+        Some(addr - self.range_start + self.offset)
+    }
+    /// Converts an offset in the mapped file to the address that it is mapped
+    /// at, the inverse of [`to_file_offset`](#method.to_file_offset). Returns
+    /// `None` if that part of the file isn't mapped by this region.
+    pub fn file_offset_to_addr(&self, offset: usize) -> Option<usize> {
+        if self.pathname.is_none() || offset < self.offset || offset - self.offset >= self.size() {
+            return None;
+        }
+        Some(self.range_start + (offset - self.offset))
+    }
+    /// Converts an address in this region to the virtual address it has in
+    /// the mapped ELF file, which is what symbol tables and debug info use.
+    /// `headers` are the program headers of the mapped file, from
+    /// [`read_program_headers`](fn.read_program_headers.html) or
+    /// [`Module::program_headers`](struct.Module.html#method.program_headers).
+    ///
+    /// The conversion uses the `PT_LOAD` segment that this region maps, so it
+    /// works for regions that map part of a segment (such as after the
+    /// loader makes the start of the data segment read-only) and for
+    /// prelinked files that are loaded at their own virtual addresses. Returns
+    /// `None` if the address is outside the region, or no segment matches.
+    pub fn to_elf_vaddr(&self, addr: usize, headers: &[ProgramHeader]) -> Option<usize> {
+        let offset = self.to_file_offset(addr)? as u64;
+        let segment = self.load_segment(headers)?;
+        Some(
+            segment
+                .p_vaddr
+                .wrapping_add(offset)
+                .wrapping_sub(segment.p_offset) as usize,
+        )
+    }
+    /// Converts a virtual address in the mapped ELF file to the address it is
+    /// loaded at, the inverse of [`to_elf_vaddr`](#method.to_elf_vaddr).
+    /// Returns `None` if the virtual address isn't mapped by this region.
+    pub fn elf_vaddr_to_addr(&self, vaddr: usize, headers: &[ProgramHeader]) -> Option<usize> {
+        let segment = self.load_segment(headers)?;
+        let offset = (vaddr as u64)
+            .wrapping_sub(segment.p_vaddr)
+            .wrapping_add(segment.p_offset);
+        if offset > usize::MAX as u64 {
+            return None;
+        }
+        self.file_offset_to_addr(offset as usize)
+    }
+
+    /// Finds the `PT_LOAD` segment that this region maps. The last page of a
+    /// segment is often mapped again as the first page of the next one, so
+    /// segments with the region's permissions are preferred, and then the
+    /// one that covers the most of the region.
+    fn load_segment<'a>(&self, headers: &'a [ProgramHeader]) -> Option<&'a ProgramHeader> {
+        let start = self.offset as u64;
+        let end = start + self.size() as u64;
+        headers
+            .iter()
+            .filter(|h| h.p_type == PT_LOAD)
+            .filter_map(|h| {
+                let overlap = end
+                    .min(h.p_offset.saturating_add(h.p_filesz))
+                    .saturating_sub(start.max(h.p_offset));
+                // The loader can make a writable segment read-only, but never
+                // changes whether it is executable
+                let same_perms = (h.p_flags & PF_X != 0) == self.is_exec()
+                    && (h.p_flags & PF_W != 0 || !self.is_write());
+                if overlap > 0 {
+                    Some(((same_perms, overlap), h))
+                } else {
+                    None
+                }
+            })
+            .max_by_key(|&(key, _)| key)
+            .map(|(_, h)| h)
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_file_offsets() {
+    let maps = super::parse_maps(include_str!("../../ci/testdata/map_elf.txt")).unwrap();
+    let text = &maps[1];
+    assert_eq!(text.to_file_offset(0x564b4d662000), Some(0x8e000));
+    assert_eq!(text.to_file_offset(0x564b4d663234), Some(0x8f234));
+    assert_eq!(text.to_file_offset(0x564b4d81a000), None);
+    assert_eq!(text.to_file_offset(0x564b4d661fff), None);
+    assert_eq!(text.file_offset_to_addr(0x8f234), Some(0x564b4d663234));
+    assert_eq!(text.file_offset_to_addr(0x8dfff), None);
+    assert_eq!(text.file_offset_to_addr(0x246000), None);
+
+    // Anonymous memory doesn't map a file, even though it has an offset
+    let bss = &maps[5];
+    assert_eq!(bss.to_file_offset(0x564b4d834000), None);
+    assert_eq!(bss.file_offset_to_addr(0), None);
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_elf_vaddrs() {
+    let load = |p_flags, p_offset, p_vaddr, p_filesz, p_memsz, p_align| ProgramHeader {
+        p_type: PT_LOAD,
+        p_flags,
+        p_offset,
+        p_vaddr,
+        p_filesz,
+        p_memsz,
+        p_align,
+    };
+    let maps = super::parse_maps(include_str!("../../ci/testdata/map_elf.txt")).unwrap();
+
+    // A position independent executable whose data segment was split by
+    // RELRO, and whose last segment shares a page of the file with the one
+    // before it
+    let pie = [
+        load(4, 0, 0, 0x8e1ec, 0x8e1ec, 0x1000),
+        load(5, 0x8e1f0, 0x8f1f0, 0x1b71f0, 0x1b71f0, 0x1000),
+        load(6, 0x2453e0, 0x2473e0, 0x17fa8, 0x18c20, 0x1000),
+        load(6, 0x25d388, 0x260388, 0x9e8, 0xaf0, 0x1000),
+    ];
+    let bias = 0x564b4d5d3000;
+    for map in &maps[..5] {
+        for &addr in &[
+            map.start(),
+            map.start() + 0x388,
+            map.start() + map.size() - 1,
+        ] {
+            assert_eq!(map.to_elf_vaddr(addr, &pie), Some(addr - bias));
+            assert_eq!(map.elf_vaddr_to_addr(addr - bias, &pie), Some(addr));
+        }
+    }
+    assert_eq!(maps[1].to_elf_vaddr(0x564b4d81a000, &pie), None);
+    assert_eq!(maps[1].elf_vaddr_to_addr(0x8f1ef - 0x1000, &pie), None);
+    assert_eq!(maps[5].to_elf_vaddr(0x564b4d834000, &pie), None);
+
+    // A prelinked library, which is loaded at its virtual addresses unless
+    // that range is taken
+    let prelinked = [
+        load(5, 0, 0x3a000000, 0x1e00, 0x1e00, 0x200000),
+        load(6, 0x1e10, 0x3a201e10, 0x200, 0x400, 0x200000),
+    ];
+    assert_eq!(
+        maps[6].to_elf_vaddr(0x3a000100, &prelinked),
+        Some(0x3a000100)
+    );
+    assert_eq!(
+        maps[7].to_elf_vaddr(0x3a201e10, &prelinked),
+        Some(0x3a201e10)
+    );
+    assert_eq!(
+        maps[8].to_elf_vaddr(0x7f1a00000100, &prelinked),
+        Some(0x3a000100)
+    );
+    assert_eq!(
+        maps[9].to_elf_vaddr(0x7f1a00201e10, &prelinked),
+        Some(0x3a201e10)
+    );
+    assert_eq!(
+        maps[9].elf_vaddr_to_addr(0x3a201e10, &prelinked),
+        Some(0x7f1a00201e10)
+    );
+    assert_eq!(maps[9].elf_vaddr_to_addr(0x3a000100, &prelinked), None);
+
+    // Without a segment that covers the mapping there's no answer
+    assert_eq!(maps[1].to_elf_vaddr(0x564b4d662000, &prelinked), None);
+    assert_eq!(maps[0].to_elf_vaddr(0x564b4d5d3000, &[]), None);
+}
+
+#[test]
+fn test_read_program_headers() {
+    let headers = read_program_headers(std::io::Cursor::new(
+        &include_bytes!("../../ci/testdata/core64.elf")[..],
+    ))
+    .unwrap();
+    assert_eq!(headers.iter().filter(|h| h.p_type == PT_LOAD).count(), 7);
+}
@@ -0,0 +1,245 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std;
+use std::fmt;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::fs::File;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::io::{BufReader, Read, Seek, SeekFrom};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::unix::fs::FileExt;
+use std::str::FromStr;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::{MapRange, Pid, ProcFs};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use elf::{self, ElfReader};
+
+/// The GNU build ID of an ELF file, from its `NT_GNU_BUILD_ID` note.
+///
+/// Build IDs are usually a 20 byte SHA-1 hash chosen by the linker, and are
+/// written as lowercase hex by `Display`, which is the form that symbol
+/// servers and `debuginfod` look them up by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BuildId(Vec<u8>);
+
+impl BuildId {
+    /// Creates a BuildId from the raw bytes of the note's descriptor
+    pub fn new(bytes: Vec<u8>) -> BuildId {
+        BuildId(bytes)
+    }
+    /// Returns the raw bytes of the build ID
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for BuildId {
+    /// Writes the build ID as lowercase hex
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BuildId {
+    type Err = std::io::Error;
+
+    /// Parses a build ID written as hex
+    fn from_str(s: &str) -> Result<BuildId, std::io::Error> {
+        let invalid = || {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid build ID {:?}", s),
+            )
+        };
+        if s.len() & 1 == 1 || !s.is_ascii() {
+            return Err(invalid());
+        }
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| invalid()))
+            .collect::<Result<Vec<u8>, _>>()
+            .map(BuildId)
+    }
+}
+
+/// Serializes as the lowercase hex string
+#[cfg(feature = "serde")]
+impl Serialize for BuildId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for BuildId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BuildId, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        hex.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Gets the maps of the passed in PID like
+/// [`get_process_maps`](fn.get_process_maps.html), with the
+/// [`BuildId`](struct.BuildId.html) of each loaded ELF file attached to every
+/// one of its mappings.
+///
+/// Build IDs are found the same way as for
+/// [`get_process_modules`](fn.get_process_modules.html).
+pub fn get_process_maps_with_build_ids(pid: Pid) -> std::io::Result<Vec<MapRange>> {
+    ProcFs::default().get_process_maps_with_build_ids(pid)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl ProcFs {
+    /// Gets the maps of the passed in PID from this procfs with their build
+    /// IDs attached, like
+    /// [`get_process_maps_with_build_ids`](fn.get_process_maps_with_build_ids.html)
+    pub fn get_process_maps_with_build_ids(&self, pid: Pid) -> std::io::Result<Vec<MapRange>> {
+        let mut maps = self.get_process_maps(pid)?;
+        let modules = self.modules_from_maps(pid, maps.clone());
+        for map in maps.iter_mut() {
+            let module = modules
+                .iter()
+                .find(|m| m.segments().iter().any(|s| s.start() == map.start()));
+            if let Some(module) = module {
+                map.build_id = module.build_id().cloned();
+            }
+        }
+        Ok(maps)
+    }
+
+    /// Reads the build ID of the ELF file whose lowest-offset mapping is
+    /// `first`, from the copy loaded in the process's memory if possible,
+    /// and otherwise from the file
+    pub(super) fn read_build_id(
+        &self,
+        pid: Pid,
+        mem: Option<&File>,
+        first: &MapRange,
+        page_size: u64,
+    ) -> Option<BuildId> {
+        mem.and_then(|mem| memory_build_id(mem, first, page_size))
+            .or_else(|| file_build_id(self.open_module_file(pid, first)?))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Reads the build ID from the ELF headers mapped at the start of `first`.
+/// The loaded copy is what is actually running, even if the file has since
+/// been deleted or replaced.
+fn memory_build_id(mem: &File, first: &MapRange, page_size: u64) -> Option<BuildId> {
+    // Only the mapping of the start of the file holds the headers
+    if first.offset != 0 {
+        return None;
+    }
+    let base = first.start() as u64;
+    let mut elf = ElfReader::new(MemoryReader { mem, base, pos: 0 }).ok()?;
+    let headers = elf.program_headers().ok()?;
+    let bias = elf::load_bias(&headers, base, 0, page_size)?;
+    // Notes are loaded at their virtual address, which the reader wants
+    // relative to the headers
+    elf::build_id(&mut elf, &headers, |h| {
+        bias.wrapping_add(h.p_vaddr).wrapping_sub(base)
+    })
+    .map(BuildId)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Reads the build ID from the note segments stored in an ELF file
+fn file_build_id(file: File) -> Option<BuildId> {
+    let mut elf = ElfReader::new(BufReader::new(file)).ok()?;
+    let headers = elf.program_headers().ok()?;
+    elf::build_id(&mut elf, &headers, |h| h.p_offset).map(BuildId)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Reads a process's memory through `/proc/PID/mem`, with positions relative
+/// to `base`
+struct MemoryReader<'a> {
+    mem: &'a File,
+    base: u64,
+    pos: u64,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl<'a> Read for MemoryReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.mem.read_at(buf, self.base.wrapping_add(self.pos))?;
+        self.pos = self.pos.wrapping_add(read as u64);
+        Ok(read)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl<'a> Seek for MemoryReader<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = match pos {
+            SeekFrom::Start(pos) => pos,
+            SeekFrom::Current(delta) => self.pos.wrapping_add(delta as u64),
+            SeekFrom::End(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "process memory has no end",
+                ))
+            }
+        };
+        Ok(self.pos)
+    }
+}
+
+#[test]
+fn test_parse_build_id() {
+    let id: BuildId = "93ac61ec5a8eb1396f9fbd350e3169a558528a40".parse().unwrap();
+    assert_eq!(id.as_bytes().len(), 20);
+    assert_eq!(&id.as_bytes()[..3], &[0x93, 0xac, 0x61]);
+    assert_eq!(id.to_string(), "93ac61ec5a8eb1396f9fbd350e3169a558528a40");
+    assert_eq!(BuildId::new(vec![0, 0xff]).to_string(), "00ff");
+    assert!("abc".parse::<BuildId>().is_err());
+    assert!("zz".parse::<BuildId>().is_err());
+    assert!("é1".parse::<BuildId>().is_err());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_get_process_maps_with_build_ids() {
+    use super::get_process_modules;
+
+    let pid = std::process::id() as Pid;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as u64 };
+    let procfs = ProcFs::default();
+    let mem = File::open(procfs.path(pid, "mem")).unwrap();
+
+    // The copy in memory and the file on disk agree for every module that
+    // has a build ID
+    let mut checked = 0;
+    for module in get_process_modules(pid).unwrap() {
+        let first = module.segments().iter().min_by_key(|m| m.offset).unwrap();
+        let from_file = procfs.open_module_file(pid, first).and_then(file_build_id);
+        if from_file.is_some() {
+            assert_eq!(
+                memory_build_id(&mem, first, page_size),
+                from_file,
+                "{:?}",
+                module.filename()
+            );
+            assert_eq!(module.build_id(), from_file.as_ref());
+            checked += 1;
+        }
+    }
+    assert!(checked > 0);
+
+    // Every mapping of a module carries its build ID, and other regions don't
+    let maps = get_process_maps_with_build_ids(pid).unwrap();
+    let with_ids: Vec<&MapRange> = maps.iter().filter(|m| m.build_id().is_some()).collect();
+    assert!(!with_ids.is_empty());
+    assert!(with_ids.iter().all(|m| m.filename().is_some()));
+    assert!(maps
+        .iter()
+        .filter(|m| m.filename().is_none())
+        .all(|m| m.build_id().is_none()));
+}
@@ -0,0 +1,197 @@
+use std;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use super::iter::bytes_to_path;
+use super::{DeviceNumber, MapRange, DELETED_SUFFIX};
+use elf::{self, invalid_data, ElfReader};
+use Permissions;
+
+/// `NT_FILE`, the note that lists the files mapped into the process
+const NT_FILE: u32 = 0x4649_4c45;
+
+/// A file mapping from the `NT_FILE` note
+struct FileMapping<'a> {
+    start: u64,
+    end: u64,
+    offset: u64,
+    name: &'a [u8],
+}
+
+/// Reads the memory maps that were recorded in an ELF core dump.
+///
+/// See [`parse_core_dump`](fn.parse_core_dump.html) for the details.
+pub fn get_core_dump_maps<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<MapRange>> {
+    parse_core_dump(BufReader::new(File::open(path)?))
+}
+
+/// Reads the memory maps that were recorded in an ELF core dump from
+/// `reader`, which works for both 32 and 64 bit little-endian cores.
+///
+/// Every `PT_LOAD` segment becomes a MapRange, with the permissions from its
+/// flags. The pathnames and file offsets come from the `NT_FILE` note, which
+/// Linux has written since 3.7, so anonymous ranges and all ranges in older
+/// cores have no pathname. The device, inode and shared flag aren't recorded
+/// in core dumps and are left as zero.
+pub fn parse_core_dump<R: Read + Seek>(reader: R) -> std::io::Result<Vec<MapRange>> {
+    let mut elf = ElfReader::new(reader)?;
+    if elf.header().e_type != elf::ET_CORE {
+        return Err(invalid_data("not an ELF core dump"));
+    }
+    let is_64 = elf.header().is_64;
+    let headers = elf.program_headers()?;
+
+    let mut file_notes = Vec::new();
+    for header in headers.iter().filter(|h| h.p_type == elf::PT_NOTE) {
+        let data = elf.read_segment(header)?;
+        for note in elf::notes(&data, header.p_align) {
+            if note.name == b"CORE" && note.n_type == NT_FILE {
+                file_notes.push(note.desc.to_vec());
+            }
+        }
+    }
+    let mut files = Vec::new();
+    for desc in file_notes.iter() {
+        files.extend(parse_file_note(desc, is_64)?);
+    }
+
+    let mut ranges = Vec::new();
+    for header in headers.iter().filter(|h| h.p_type == elf::PT_LOAD) {
+        let end = header
+            .p_vaddr
+            .checked_add(header.p_memsz)
+            .ok_or_else(|| invalid_data("segment wraps the address space"))?;
+        let file = files
+            .iter()
+            .find(|f| f.start == header.p_vaddr && f.end == end);
+        let (pathname, deleted) = match file {
+            Some(file) => {
+                let name = file.name;
+                let deleted = name.ends_with(DELETED_SUFFIX.as_bytes());
+                let name = if deleted {
+                    &name[..name.len() - DELETED_SUFFIX.len()]
+                } else {
+                    name
+                };
+                (
+                    Some(bytes_to_path(Cow::Borrowed(name)).into_owned()),
+                    deleted,
+                )
+            }
+            None => (None, false),
+        };
+        ranges.push(MapRange {
+            range_start: to_usize(header.p_vaddr)?,
+            range_end: to_usize(end)?,
+            offset: to_usize(file.map_or(0, |f| f.offset))?,
+            dev: DeviceNumber::default(),
+            perms: Permissions::new(
+                header.p_flags & elf::PF_R != 0,
+                header.p_flags & elf::PF_W != 0,
+                header.p_flags & elf::PF_X != 0,
+                false,
+            ),
+            inode: 0,
+            pathname,
+            deleted,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        });
+    }
+    Ok(ranges)
+}
+
+/// Parses an `NT_FILE` note, which holds the number of files and the page
+/// size, then a `(start, end, page offset)` triple for each file, and finally
+/// the nul-terminated filenames
+fn parse_file_note(desc: &[u8], is_64: bool) -> std::io::Result<Vec<FileMapping<'_>>> {
+    let word = if is_64 { 8 } else { 4 };
+    let truncated = || invalid_data("truncated NT_FILE note");
+    if desc.len() < 2 * word {
+        return Err(truncated());
+    }
+    let count = elf::read_word(desc, 0, is_64) as usize;
+    let page_size = elf::read_word(desc, word, is_64);
+    let names_start = count
+        .checked_mul(3 * word)
+        .and_then(|size| size.checked_add(2 * word))
+        .filter(|&start| start <= desc.len())
+        .ok_or_else(truncated)?;
+
+    let mut names = desc[names_start..].split(|&b| b == 0);
+    let mut files = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = 2 * word + i * 3 * word;
+        let page_offset = elf::read_word(desc, entry + 2 * word, is_64);
+        files.push(FileMapping {
+            start: elf::read_word(desc, entry, is_64),
+            end: elf::read_word(desc, entry + word, is_64),
+            offset: page_offset
+                .checked_mul(page_size)
+                .ok_or_else(|| invalid_data("invalid NT_FILE offset"))?,
+            name: names.next().ok_or_else(truncated)?,
+        });
+    }
+    Ok(files)
+}
+
+fn to_usize(value: u64) -> std::io::Result<usize> {
+    if value > usize::MAX as u64 {
+        return Err(invalid_data("address doesn't fit in usize"));
+    }
+    Ok(value as usize)
+}
+
+#[test]
+fn test_parse_core_dump() {
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    let core64 = parse_core_dump(Cursor::new(
+        &include_bytes!("../../ci/testdata/core64.elf")[..],
+    ));
+    let core32 = parse_core_dump(Cursor::new(
+        &include_bytes!("../../ci/testdata/core32.elf")[..],
+    ));
+    let cores = [
+        (core64.unwrap(), 0x55d0c1e00000u64, 0x7f0b4f000000u64),
+        (core32.unwrap(), 0x56555000, 0xf7f00000),
+    ];
+    for &(ref ranges, base, lib) in cores.iter() {
+        let (base, lib) = (base as usize, lib as usize);
+        assert_eq!(ranges.len(), 7);
+
+        let sleep = Some(PathBuf::from("/usr/bin/sleep"));
+        assert_eq!(ranges[0].start(), base);
+        assert_eq!(ranges[0].size(), 0x2000);
+        assert_eq!(ranges[0].filename(), sleep.as_deref());
+        assert_eq!(ranges[0].perms, Permissions::new(true, false, false, false));
+        assert_eq!(ranges[1].offset, 0x2000);
+        assert!(ranges[1].is_exec());
+        assert!(!ranges[1].is_write());
+        assert_eq!(ranges[2].offset, 0x7000);
+        assert!(ranges[2].is_write());
+
+        // The heap and stack aren't files, so they have no names
+        assert_eq!(ranges[3].start(), base + 0x9000);
+        assert_eq!(ranges[3].filename(), None);
+        assert_eq!(ranges[3].offset, 0);
+        assert_eq!(ranges[6].filename(), None);
+
+        assert_eq!(ranges[5].start(), lib + 0x1000);
+        assert_eq!(ranges[5].filename(), Some(Path::new("/tmp/lib gone.so")));
+        assert!(ranges[5].is_deleted());
+        assert_eq!(ranges[5].offset, 0x1000);
+        assert!(!ranges[4].is_exec());
+    }
+
+    let maps = include_bytes!("../../ci/testdata/map.txt");
+    assert!(parse_core_dump(Cursor::new(&maps[..])).is_err());
+    // A shared library isn't a core dump
+    let mut not_core = include_bytes!("../../ci/testdata/core64.elf").to_vec();
+    not_core[16] = 3;
+    assert!(parse_core_dump(Cursor::new(not_core)).is_err());
+}
@@ -0,0 +1,336 @@
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use libc;
+use std;
+use std::ffi::OsString;
+use std::fmt;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::fs::Metadata;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::unix::fs::MetadataExt;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use super::{unescape_os_string, ParseError, ParseField};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::{Pid, ProcFs};
+
+/// The device number of the filesystem backing a mapping, as shown in the
+/// `dev` column of `/proc/PID/maps`.
+///
+/// Anonymous mappings have a device number of `00:00`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceNumber {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl DeviceNumber {
+    /// Creates a DeviceNumber from its major and minor numbers
+    pub fn new(major: u32, minor: u32) -> DeviceNumber {
+        DeviceNumber { major, minor }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    /// Splits a `dev_t`, such as the one returned by `MetadataExt::dev`, into
+    /// its major and minor numbers
+    // dev_t is only 32 bits on some Android targets
+    #[allow(clippy::unnecessary_cast)]
+    pub fn from_dev_t(dev: libc::dev_t) -> DeviceNumber {
+        let dev = dev as u64;
+        DeviceNumber {
+            major: (((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0x0000_0fff)) as u32,
+            minor: (((dev >> 12) & 0xffff_ff00) | (dev & 0x0000_00ff)) as u32,
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    /// Returns the device number encoded as a `dev_t`
+    pub fn dev_t(&self) -> libc::dev_t {
+        let major = self.major as u64;
+        let minor = self.minor as u64;
+        (((major & 0xffff_f000) << 32)
+            | ((major & 0x0000_0fff) << 8)
+            | ((minor & 0xffff_ff00) << 12)
+            | (minor & 0x0000_00ff)) as libc::dev_t
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    /// Returns whether this is the device that holds the file described by
+    /// `metadata`
+    pub fn matches_metadata(&self, metadata: &Metadata) -> bool {
+        DeviceNumber::from_dev_t(metadata.dev() as libc::dev_t) == *self
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    /// Returns the block device node for this device number, by following the
+    /// `/sys/dev/block/MAJOR:MINOR` link. Returns `None` for devices that
+    /// aren't block devices, such as the anonymous devices used by tmpfs,
+    /// overlayfs and other virtual filesystems.
+    ///
+    /// This always reads the local `/sys`, even for devices that came from a
+    /// [`ProcFs`](struct.ProcFs.html) with another root; use
+    /// [`block_device_in`](#method.block_device_in) for a sysfs mounted
+    /// elsewhere.
+    pub fn block_device(&self) -> std::io::Result<Option<PathBuf>> {
+        self.block_device_in("/sys")
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    /// Returns the block device node for this device number like
+    /// [`block_device`](#method.block_device), from the sysfs mounted at
+    /// `sysfs`. The returned path is still under `/dev`, since that is where
+    /// the node is in the system that the sysfs belongs to.
+    pub fn block_device_in<P: AsRef<Path>>(&self, sysfs: P) -> std::io::Result<Option<PathBuf>> {
+        let link = sysfs
+            .as_ref()
+            .join(format!("dev/block/{}:{}", self.major, self.minor));
+        match std::fs::read_link(&link) {
+            Ok(target) => Ok(target
+                .file_name()
+                .map(|name| PathBuf::from("/dev").join(name))),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    /// Returns the mounts in the passed in PID's mount namespace that are
+    /// backed by this device, from `/proc/PID/mountinfo`
+    pub fn mounts(&self, pid: Pid) -> std::io::Result<Vec<MountInfo>> {
+        ProcFs::default().device_mounts(pid, *self)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl ProcFs {
+    /// Returns every mount in the passed in PID's mount namespace, from the
+    /// `mountinfo` file in this procfs
+    pub fn mountinfo(&self, pid: Pid) -> std::io::Result<Vec<MountInfo>> {
+        let mut contents = Vec::new();
+        self.read_bytes(pid, "mountinfo", &mut contents)?;
+        parse_mountinfo(&contents)
+    }
+
+    /// Returns the mounts in the passed in PID's mount namespace that are
+    /// backed by `dev`, like [`DeviceNumber::mounts`](struct.DeviceNumber.html#method.mounts)
+    pub fn device_mounts(&self, pid: Pid, dev: DeviceNumber) -> std::io::Result<Vec<MountInfo>> {
+        Ok(self
+            .mountinfo(pid)?
+            .into_iter()
+            .filter(|m| m.dev == dev)
+            .collect())
+    }
+}
+
+impl FromStr for DeviceNumber {
+    type Err = ParseError;
+
+    /// Parses the hexadecimal `MAJOR:MINOR` format used by `/proc/PID/maps`
+    fn from_str(s: &str) -> Result<DeviceNumber, ParseError> {
+        let mut split = s.split(':');
+        match (split.next(), split.next(), split.next()) {
+            (Some(major), Some(minor), None) => {
+                match (
+                    u32::from_str_radix(major, 16),
+                    u32::from_str_radix(minor, 16),
+                ) {
+                    (Ok(major), Ok(minor)) => Ok(DeviceNumber { major, minor }),
+                    _ => Err(ParseError::new(ParseField::Dev, s.as_bytes())),
+                }
+            }
+            _ => Err(ParseError::new(ParseField::Dev, s.as_bytes())),
+        }
+    }
+}
+
+impl fmt::Display for DeviceNumber {
+    /// Writes the device number in the `/proc/PID/maps` format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02x}:{:02x}", self.major, self.minor)
+    }
+}
+
+/// A single mount from `/proc/PID/mountinfo`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MountInfo {
+    pub mount_id: u32,
+    pub parent_id: u32,
+    pub dev: DeviceNumber,
+    /// The directory of the filesystem that forms the root of this mount
+    pub root: PathBuf,
+    pub mount_point: PathBuf,
+    /// The filesystem type, such as `ext4`, `tmpfs` or `overlay`
+    pub fs_type: String,
+    /// The filesystem specific source, such as `/dev/sda1`
+    pub source: OsString,
+}
+
+fn parse_mountinfo_line(line: &[u8]) -> Result<MountInfo, ParseError> {
+    let mut split = line
+        .split(|&b| b == b' ' || b == b'\t')
+        .filter(|s| !s.is_empty());
+    let mount_id = parse_number(next_field(&mut split, ParseField::Field)?)?;
+    let parent_id = parse_number(next_field(&mut split, ParseField::Field)?)?;
+    let dev = next_field(&mut split, ParseField::Dev)?;
+    let dev = match parse_decimal_dev(dev) {
+        Some(dev) => dev,
+        None => return Err(ParseError::new(ParseField::Dev, dev)),
+    };
+    let root = unescape_os_string(next_field(&mut split, ParseField::Path)?);
+    let mount_point = unescape_os_string(next_field(&mut split, ParseField::Path)?);
+    // Skip the mount options and the optional fields, which end with a "-"
+    if !split.any(|s| s == b"-") {
+        return Err(ParseError::new(ParseField::Field, b""));
+    }
+    // Whitespace in paths is escaped, so only the numeric fields and the
+    // filesystem type need to be text
+    let fs_type = next_field(&mut split, ParseField::Field)?;
+    let fs_type = match std::str::from_utf8(fs_type) {
+        Ok(fs_type) => fs_type.to_string(),
+        Err(_) => return Err(ParseError::new(ParseField::Field, fs_type)),
+    };
+    let source = unescape_os_string(next_field(&mut split, ParseField::Field)?);
+    Ok(MountInfo {
+        mount_id,
+        parent_id,
+        dev,
+        root: PathBuf::from(root),
+        mount_point: PathBuf::from(mount_point),
+        fs_type,
+        source,
+    })
+}
+
+fn next_field<'a, I: Iterator<Item = &'a [u8]>>(
+    split: &mut I,
+    field: ParseField,
+) -> Result<&'a [u8], ParseError> {
+    split.next().ok_or_else(|| ParseError::new(field, b""))
+}
+
+fn parse_number(field: &[u8]) -> Result<u32, ParseError> {
+    std::str::from_utf8(field)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| ParseError::new(ParseField::Field, field))
+}
+
+/// Parses the decimal `MAJOR:MINOR` format used by `mountinfo`
+fn parse_decimal_dev(dev: &[u8]) -> Option<DeviceNumber> {
+    let mut numbers = std::str::from_utf8(dev).ok()?.split(':');
+    match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(major), Some(minor), None) => Some(DeviceNumber {
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+fn parse_mountinfo(contents: &[u8]) -> std::io::Result<Vec<MountInfo>> {
+    let mut vec = Vec::new();
+    for (i, line) in contents.split(|&b| b == b'\n').enumerate() {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            break;
+        }
+        vec.push(parse_mountinfo_line(line).map_err(|e| e.at_line(i + 1))?);
+    }
+    Ok(vec)
+}
+
+#[test]
+fn test_parse_device_number() {
+    let dev: DeviceNumber = "fd:01".parse().unwrap();
+    assert_eq!(dev, DeviceNumber::new(253, 1));
+    assert_eq!(dev.to_string(), "fd:01");
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    assert_eq!(DeviceNumber::from_dev_t(dev.dev_t()), dev);
+
+    let big = DeviceNumber::new(0x1234, 0x56789);
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    assert_eq!(DeviceNumber::from_dev_t(big.dev_t()), big);
+    assert_eq!(big.to_string(), "1234:56789");
+    assert_eq!("1234:56789".parse::<DeviceNumber>().unwrap(), big);
+
+    assert!("fd".parse::<DeviceNumber>().is_err());
+    assert!("fd:01:02".parse::<DeviceNumber>().is_err());
+    let err = "fd:zz".parse::<DeviceNumber>().unwrap_err();
+    assert_eq!(err.field(), ParseField::Dev);
+    assert_eq!(err.text(), "fd:zz");
+}
+
+#[test]
+fn test_parse_mountinfo() {
+    let mounts = parse_mountinfo(include_bytes!("../../ci/testdata/mountinfo.txt")).unwrap();
+    assert_eq!(mounts.len(), 7);
+    assert_eq!(mounts[0].dev, DeviceNumber::new(253, 1));
+    assert_eq!(mounts[0].mount_point, PathBuf::from("/"));
+    assert_eq!(mounts[0].fs_type, "ext4");
+    assert_eq!(mounts[0].source, OsString::from("/dev/mapper/root"));
+    assert_eq!(mounts[4].fs_type, "tmpfs");
+    assert_eq!(mounts[5].fs_type, "overlay");
+    assert_eq!(mounts[5].parent_id, 22);
+    assert_eq!(mounts[6].root, PathBuf::from("/home/user/My Projects"));
+
+    let err = parse_mountinfo(b"22 1 253:1 / / rw - ext4 /dev/sda1 rw\n23 22 0-21 / /proc rw\n")
+        .unwrap_err();
+    let err = err.get_ref().unwrap().downcast_ref::<ParseError>().unwrap();
+    assert_eq!(
+        (err.line(), err.field(), err.text()),
+        (2, ParseField::Dev, "0-21")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_parse_mountinfo_non_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let line = b"40 22 8:1 /caf\xe9 /mnt/caf\xe9\\040bar rw - vfat /dev/sd\xe91 rw";
+    let mounts = parse_mountinfo(line).unwrap();
+    assert_eq!(mounts.len(), 1);
+    assert_eq!(mounts[0].root.as_os_str().as_bytes(), b"/caf\xe9");
+    assert_eq!(
+        mounts[0].mount_point.as_os_str().as_bytes(),
+        b"/mnt/caf\xe9 bar"
+    );
+    assert_eq!(mounts[0].source.as_bytes(), b"/dev/sd\xe91");
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_device_matches_metadata() {
+    let exe = std::env::current_exe().unwrap();
+    let metadata = std::fs::metadata(&exe).unwrap();
+    let maps = super::get_process_maps(std::process::id() as Pid).unwrap();
+    let map = maps
+        .iter()
+        .find(|m| m.filename() == Some(exe.as_path()))
+        .unwrap();
+    assert!(map.dev.matches_metadata(&metadata));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_block_device_in() {
+    use std::fs::{create_dir_all, remove_dir_all};
+
+    let sysfs = std::env::temp_dir().join(format!("proc-maps-sysfs-{}", std::process::id()));
+    let _ = remove_dir_all(&sysfs);
+    create_dir_all(sysfs.join("dev/block")).unwrap();
+    std::os::unix::fs::symlink(
+        "../../devices/pci0000:00/0000:00:1f.2/ata1/host0/target0:0:0/0:0:0:0/block/sda/sda1",
+        sysfs.join("dev/block/8:1"),
+    )
+    .unwrap();
+
+    let sda1 = DeviceNumber::new(8, 1).block_device_in(&sysfs);
+    let tmpfs = DeviceNumber::new(0, 42).block_device_in(&sysfs);
+    remove_dir_all(&sysfs).unwrap();
+    assert_eq!(sda1.unwrap(), Some(PathBuf::from("/dev/sda1")));
+    assert_eq!(tmpfs.unwrap(), None);
+}
@@ -0,0 +1,102 @@
+use std;
+use std::error::Error;
+use std::fmt;
+
+/// The field of a line that failed to parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseField {
+    /// The `start-end` address range, or the start address in `numa_maps`
+    Range,
+    /// The `rwxp` permissions
+    Perms,
+    Offset,
+    Dev,
+    Inode,
+    /// The pathname, which can only fail when it can't be represented as a
+    /// path on the current platform
+    Path,
+    /// The memory policy in `numa_maps`
+    Policy,
+    /// Any other field, such as a `Key: value` line of `smaps`, a `key=value`
+    /// entry of `numa_maps`, or a column of `mountinfo` or `stat`
+    Field,
+}
+
+impl fmt::Display for ParseField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ParseField::Range => "address range",
+            ParseField::Perms => "permissions",
+            ParseField::Offset => "offset",
+            ParseField::Dev => "device",
+            ParseField::Inode => "inode",
+            ParseField::Path => "pathname",
+            ParseField::Policy => "memory policy",
+            ParseField::Field => "field",
+        })
+    }
+}
+
+/// An error from parsing a line of `/proc/PID/maps`, or of one of the other
+/// files in `/proc/PID` such as `smaps`, `numa_maps` or `mountinfo`.
+///
+/// Functions that return `std::io::Result` wrap this in an error of kind
+/// `InvalidInput`, and it can be recovered with
+/// `error.get_ref().and_then(|e| e.downcast_ref::<ParseError>())`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    line: usize,
+    field: ParseField,
+    text: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(field: ParseField, text: &[u8]) -> ParseError {
+        ParseError {
+            line: 1,
+            field,
+            text: String::from_utf8_lossy(text).into_owned(),
+        }
+    }
+
+    pub(crate) fn at_line(mut self, line: usize) -> ParseError {
+        self.line = line;
+        self
+    }
+
+    /// Returns the 1-based number of the line that failed to parse
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    /// Returns the field that failed to parse
+    pub fn field(&self) -> ParseField {
+        self.field
+    }
+    /// Returns the text of the field that failed to parse, which is empty if
+    /// the field was missing
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.text.is_empty() {
+            write!(f, "missing {} on line {}", self.field, self.line)
+        } else {
+            write!(
+                f,
+                "invalid {} {:?} on line {}",
+                self.field, self.text, self.line
+            )
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for std::io::Error {
+    fn from(err: ParseError) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
+    }
+}
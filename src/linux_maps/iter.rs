@@ -0,0 +1,357 @@
+use std;
+use std::borrow::Cow;
+#[cfg(unix)]
+use std::ffi::{OsStr, OsString};
+#[cfg(unix)]
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+use super::{DeviceNumber, MapRange, ParseError, ParseField, DELETED_SUFFIX};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::{Pid, ProcFs};
+use Permissions;
+
+/// A borrowed view of a single line of `/proc/PID/maps`.
+///
+/// This is what [`MapsIter`](struct.MapsIter.html) yields, and avoids the
+/// allocations that building a [`MapRange`](struct.MapRange.html) needs. Use
+/// [`to_map_range`](#method.to_map_range) to get an owned copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapRangeRef<'a> {
+    range_start: usize,
+    range_end: usize,
+    pub offset: usize,
+    pub dev: DeviceNumber,
+    pub perms: Permissions,
+    pub inode: usize,
+    pathname: Option<&'a [u8]>,
+    deleted: bool,
+}
+
+impl<'a> MapRangeRef<'a> {
+    /// Parses a single line in the format shared by `/proc/PID/maps` and the
+    /// header lines of `/proc/PID/smaps`
+    pub fn parse(line: &'a [u8]) -> Result<MapRangeRef<'a>, ParseError> {
+        let mut fields = Fields { rest: line };
+
+        let range = fields.next(ParseField::Range)?;
+        let (range_start, range_end) = match parse_range(range) {
+            Some(range) => range,
+            None => return Err(ParseError::new(ParseField::Range, range)),
+        };
+
+        let perms = fields.next(ParseField::Perms)?;
+        let perms = match field_str(perms).and_then(|s| s.parse::<Permissions>().ok()) {
+            Some(perms) => perms,
+            None => return Err(ParseError::new(ParseField::Perms, perms)),
+        };
+        let offset = fields.next(ParseField::Offset)?;
+        let offset = match field_str(offset).and_then(|s| usize::from_str_radix(s, 16).ok()) {
+            // mmap: offset must be a multiple of the page size as returned by sysconf(_SC_PAGE_SIZE).
+            Some(i) if i & 0xfff == 0 => i,
+            _ => return Err(ParseError::new(ParseField::Offset, offset)),
+        };
+        let dev = fields.next(ParseField::Dev)?;
+        let dev = match field_str(dev).and_then(|s| s.parse::<DeviceNumber>().ok()) {
+            Some(dev) => dev,
+            None => return Err(ParseError::new(ParseField::Dev, dev)),
+        };
+        let inode = fields.next(ParseField::Inode)?;
+        let inode = match field_str(inode).and_then(|s| s.parse::<usize>().ok()) {
+            Some(inode) => inode,
+            None => return Err(ParseError::new(ParseField::Inode, inode)),
+        };
+
+        // The pathname is everything after the padding that follows the inode,
+        // and may itself contain spaces
+        let mut pathname = fields.remainder();
+        let deleted = pathname.ends_with(DELETED_SUFFIX.as_bytes());
+        if deleted {
+            pathname = &pathname[..pathname.len() - DELETED_SUFFIX.len()];
+        }
+        let pathname = Some(pathname).filter(|p| !p.is_empty());
+
+        // Paths can hold any bytes on unix, but elsewhere they need to be
+        // valid unicode
+        #[cfg(not(unix))]
+        {
+            if let Some(pathname) = pathname {
+                if std::str::from_utf8(pathname).is_err() {
+                    return Err(ParseError::new(ParseField::Path, pathname));
+                }
+            }
+        }
+
+        Ok(MapRangeRef {
+            range_start,
+            range_end,
+            offset,
+            dev,
+            perms,
+            inode,
+            pathname,
+            deleted,
+        })
+    }
+
+    /// Returns the address this range starts at
+    pub fn start(&self) -> usize {
+        self.range_start
+    }
+    /// Returns the size of this range in bytes
+    pub fn size(&self) -> usize {
+        self.range_end - self.range_start
+    }
+    /// Returns the filename of the mapped file, which is only copied if it has
+    /// a newline in it that needs unescaping
+    pub fn filename(&self) -> Option<Cow<'a, Path>> {
+        self.pathname.map(|p| bytes_to_path(unescape_newlines(p)))
+    }
+    /// Returns the raw bytes of the pathname column, without the
+    /// ` (deleted)` marker but with any escapes left in place
+    pub fn pathname_bytes(&self) -> Option<&'a [u8]> {
+        self.pathname
+    }
+    /// Returns whether the file backing this range has been deleted
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// Copies this range into an owned MapRange
+    pub fn to_map_range(&self) -> MapRange {
+        MapRange {
+            range_start: self.range_start,
+            range_end: self.range_end,
+            offset: self.offset,
+            dev: self.dev,
+            perms: self.perms,
+            inode: self.inode,
+            pathname: self.filename().map(Cow::into_owned),
+            deleted: self.deleted,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        }
+    }
+}
+
+impl<'a> From<MapRangeRef<'a>> for MapRange {
+    fn from(range: MapRangeRef<'a>) -> MapRange {
+        range.to_map_range()
+    }
+}
+
+/// Splits the whitespace separated columns off the front of a maps line
+struct Fields<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn skip_spaces(&mut self) {
+        let spaces = self
+            .rest
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count();
+        self.rest = &self.rest[spaces..];
+    }
+
+    fn next(&mut self, field: ParseField) -> Result<&'a [u8], ParseError> {
+        self.skip_spaces();
+        let len = self
+            .rest
+            .iter()
+            .take_while(|&&b| b != b' ' && b != b'\t')
+            .count();
+        if len == 0 {
+            return Err(ParseError::new(field, b""));
+        }
+        let (field, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Ok(field)
+    }
+
+    fn remainder(mut self) -> &'a [u8] {
+        self.skip_spaces();
+        self.rest
+    }
+}
+
+/// The kernel writes newlines in the pathname column as `\012`. It's the only
+/// character that gets escaped there, so a pathname with a literal `\012` in
+/// it can't be told apart from one with a newline.
+const ESCAPED_NEWLINE: &[u8] = b"\\012";
+
+fn unescape_newlines(pathname: &[u8]) -> Cow<'_, [u8]> {
+    if !pathname
+        .windows(ESCAPED_NEWLINE.len())
+        .any(|w| w == ESCAPED_NEWLINE)
+    {
+        return Cow::Borrowed(pathname);
+    }
+    let mut out = Vec::with_capacity(pathname.len());
+    let mut rest = pathname;
+    while !rest.is_empty() {
+        if rest.starts_with(ESCAPED_NEWLINE) {
+            out.push(b'\n');
+            rest = &rest[ESCAPED_NEWLINE.len()..];
+        } else {
+            out.push(rest[0]);
+            rest = &rest[1..];
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(unix)]
+pub(super) fn bytes_to_path(bytes: Cow<'_, [u8]>) -> Cow<'_, Path> {
+    match bytes {
+        Cow::Borrowed(p) => Cow::Borrowed(Path::new(OsStr::from_bytes(p))),
+        Cow::Owned(p) => Cow::Owned(PathBuf::from(OsString::from_vec(p))),
+    }
+}
+
+// MapRangeRef::parse has already checked that the pathname is valid UTF-8, so
+// nothing gets replaced here
+#[cfg(not(unix))]
+pub(super) fn bytes_to_path(bytes: Cow<'_, [u8]>) -> Cow<'_, Path> {
+    match bytes {
+        Cow::Borrowed(p) => match String::from_utf8_lossy(p) {
+            Cow::Borrowed(p) => Cow::Borrowed(Path::new(p)),
+            Cow::Owned(p) => Cow::Owned(PathBuf::from(p)),
+        },
+        Cow::Owned(p) => Cow::Owned(PathBuf::from(String::from_utf8_lossy(&p).into_owned())),
+    }
+}
+
+fn field_str(field: &[u8]) -> Option<&str> {
+    std::str::from_utf8(field).ok()
+}
+
+/// Parses the hexadecimal `start-end` address range of a maps line
+fn parse_range(range: &[u8]) -> Option<(usize, usize)> {
+    let mut range_split = field_str(range)?.split('-');
+    let range_start = usize::from_str_radix(range_split.next()?, 16).ok()?;
+    let range_end = usize::from_str_radix(range_split.next()?, 16).ok()?;
+    if range_split.next().is_some() || range_start >= range_end {
+        return None;
+    }
+    Some((range_start, range_end))
+}
+
+/// A streaming iterator over the lines of a `/proc/PID/maps` buffer, yielding
+/// borrowed [`MapRangeRef`](struct.MapRangeRef.html) items.
+///
+/// Iteration stops at the end of the buffer or at the first empty line.
+#[derive(Debug, Clone)]
+pub struct MapsIter<'a> {
+    rest: &'a [u8],
+    line: usize,
+}
+
+impl<'a> MapsIter<'a> {
+    /// Creates an iterator over the lines of a maps buffer
+    pub fn new(contents: &'a [u8]) -> MapsIter<'a> {
+        MapsIter {
+            rest: contents,
+            line: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for MapsIter<'a> {
+    type Item = Result<MapRangeRef<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, rest) = match self.rest.iter().position(|&b| b == b'\n') {
+            Some(i) => (&self.rest[..i], &self.rest[i + 1..]),
+            None => (self.rest, &self.rest[self.rest.len()..]),
+        };
+        if line.iter().all(|b| b.is_ascii_whitespace()) {
+            self.rest = &[];
+            return None;
+        }
+        self.rest = rest;
+        self.line += 1;
+        let line_number = self.line;
+        Some(MapRangeRef::parse(line).map_err(|e| e.at_line(line_number)))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Reads `/proc/PID/maps` into a buffer that is kept between calls, so that
+/// repeatedly sampling a process doesn't allocate once the buffer has grown
+/// large enough.
+#[derive(Debug, Clone, Default)]
+pub struct MapsReader {
+    procfs: ProcFs,
+    buffer: Vec<u8>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl MapsReader {
+    /// Creates a reader that reads the maps from `/proc`
+    pub fn new() -> MapsReader {
+        MapsReader::default()
+    }
+
+    /// Creates a reader that reads the maps from the passed in procfs
+    pub fn with_procfs(procfs: ProcFs) -> MapsReader {
+        MapsReader {
+            procfs,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Reads the maps of the passed in PID, returning an iterator that borrows
+    /// from this reader's buffer
+    pub fn read(&mut self, pid: Pid) -> std::io::Result<MapsIter<'_>> {
+        self.procfs.read_bytes(pid, "maps", &mut self.buffer)?;
+        Ok(MapsIter::new(&self.buffer))
+    }
+}
+
+#[test]
+fn test_maps_iter() {
+    let contents = include_bytes!("../../ci/testdata/map.txt");
+    let ranges: Vec<MapRangeRef> = MapsIter::new(contents).collect::<Result<_, _>>().unwrap();
+    assert_eq!(ranges.len(), 4);
+    assert_eq!(ranges[0].start(), 0x00400000);
+    assert_eq!(ranges[0].size(), 0x107000);
+    assert_eq!(ranges[0].pathname_bytes(), Some(&b"/usr/bin/fish"[..]));
+    assert_eq!(ranges[1].filename(), None);
+    assert!(matches!(ranges[0].filename(), Some(Cow::Borrowed(_))));
+    assert!(ranges[3].is_deleted());
+
+    let owned: Vec<MapRange> = ranges.iter().map(|r| r.to_map_range()).collect();
+    assert_eq!(owned, super::parse_proc_maps(contents).unwrap());
+
+    let mut bad = MapsIter::new(b"00400000-00507000 r-xp 00000000 00:14\n");
+    let err = bad.next().unwrap().unwrap_err();
+    assert_eq!(err.field(), ParseField::Inode);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pathname_bytes() {
+    let line = b"7f0000000000-7f0000001000 r--p 00000000 fd:01 42                         /tmp/a  b\\012c\xff (deleted)";
+    let range = MapRangeRef::parse(line).unwrap();
+    assert!(range.is_deleted());
+    assert_eq!(range.pathname_bytes(), Some(&b"/tmp/a  b\\012c\xff"[..]));
+    assert_eq!(
+        range.filename().unwrap().as_os_str().as_bytes(),
+        b"/tmp/a  b\nc\xff"
+    );
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_maps_reader() {
+    let mut reader = MapsReader::new();
+    let pid = std::process::id() as Pid;
+    let first = reader.read(pid).unwrap().count();
+    assert!(first > 0);
+    let capacity = reader.buffer.capacity();
+    assert!(reader.read(pid).unwrap().all(|r| r.is_ok()));
+    assert!(reader.buffer.capacity() >= capacity);
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+/// What kind of memory a region holds, as worked out from its pathname in
+/// `/proc/PID/maps`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RegionKind {
+    /// A mapping of a regular file
+    File,
+    /// The `[heap]` managed by `brk`
+    Heap,
+    /// The `[stack]` of the main thread
+    Stack,
+    /// The `[stack:TID]` of another thread, only shown by older kernels
+    ThreadStack(u32),
+    Vdso,
+    /// The `[vvar]` (or `[vvar_vclock]`) data pages used by the vDSO
+    Vvar,
+    Vsyscall,
+    /// The `[uprobes]` page used for uprobe trampolines
+    Uprobes,
+    /// Anonymous memory named with `PR_SET_VMA_ANON_NAME`, shown as
+    /// `[anon:NAME]` (or `[anon_shmem:NAME]` for shared memory)
+    AnonNamed(String),
+    /// A file created with `memfd_create`, holding the name it was given
+    Memfd(String),
+    /// A System V shared memory segment, holding its key
+    SysVShm(u32),
+    /// A mapping of a file that has since been deleted or replaced
+    DeletedFile,
+    /// A mapping of a device node under `/dev`
+    Device,
+    /// Anonymous memory without a name
+    Anonymous,
+    /// A pseudo-path that doesn't fit any of the other kinds, such as
+    /// `anon_inode:[perf_event]`
+    Other(String),
+}
+
+impl RegionKind {
+    /// Classifies a region from its pathname, with `None` for anonymous memory.
+    /// `deleted` is set when the kernel marked the pathname as ` (deleted)`.
+    pub fn from_pathname(pathname: Option<&Path>, deleted: bool) -> RegionKind {
+        let path = match pathname {
+            None => return RegionKind::Anonymous,
+            Some(p) => p,
+        };
+        // Pseudo-paths are always ASCII, so anything else is a real file
+        let s = match path.to_str() {
+            None => return RegionKind::File,
+            Some(s) => s,
+        };
+
+        if s.starts_with('[') && s.ends_with(']') {
+            let name = &s[1..s.len() - 1];
+            return match name {
+                "heap" => RegionKind::Heap,
+                "stack" => RegionKind::Stack,
+                "vdso" => RegionKind::Vdso,
+                "vvar" | "vvar_vclock" => RegionKind::Vvar,
+                "vsyscall" => RegionKind::Vsyscall,
+                "uprobes" => RegionKind::Uprobes,
+                _ => {
+                    if let Some(tid) = name.strip_prefix("stack:") {
+                        if let Ok(tid) = tid.parse() {
+                            return RegionKind::ThreadStack(tid);
+                        }
+                    }
+                    match name
+                        .strip_prefix("anon:")
+                        .or_else(|| name.strip_prefix("anon_shmem:"))
+                    {
+                        Some(anon) => RegionKind::AnonNamed(anon.to_string()),
+                        None => RegionKind::Other(s.to_string()),
+                    }
+                }
+            };
+        }
+
+        if let Some(name) = s.strip_prefix("/memfd:") {
+            return RegionKind::Memfd(name.to_string());
+        }
+        if let Some(key) = s.strip_prefix("/SYSV") {
+            if key.len() == 8 {
+                if let Ok(key) = u32::from_str_radix(key, 16) {
+                    return RegionKind::SysVShm(key);
+                }
+            }
+        }
+        if deleted {
+            RegionKind::DeletedFile
+        } else if !s.starts_with('/') {
+            RegionKind::Other(s.to_string())
+        } else if s.starts_with("/dev/") && !s.starts_with("/dev/shm/") {
+            RegionKind::Device
+        } else {
+            RegionKind::File
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_region_kinds() {
+    let maps = super::parse_proc_maps(include_bytes!("../../ci/testdata/map_kinds.txt")).unwrap();
+    let kinds: Vec<RegionKind> = maps.iter().map(|m| m.kind()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            RegionKind::File,
+            RegionKind::Heap,
+            RegionKind::AnonNamed("libc_malloc".to_string()),
+            RegionKind::Memfd("wayland-shm".to_string()),
+            RegionKind::SysVShm(0x162e),
+            RegionKind::DeletedFile,
+            RegionKind::Device,
+            RegionKind::File,
+            RegionKind::ThreadStack(4242),
+            RegionKind::Other("anon_inode:[perf_event]".to_string()),
+            RegionKind::AnonNamed("ring".to_string()),
+            RegionKind::Anonymous,
+            RegionKind::Stack,
+            RegionKind::Vvar,
+            RegionKind::Vvar,
+            RegionKind::Vdso,
+            RegionKind::Uprobes,
+            RegionKind::Vsyscall,
+        ]
+    );
+}
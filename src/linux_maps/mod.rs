@@ -0,0 +1,806 @@
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use libc;
+use std;
+use std::fmt;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use MapRangeImpl;
+use Permissions;
+
+mod address;
+mod build_id;
+mod core_dump;
+// The parsers that only back the procfs readers are unused on other targets
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+mod device;
+mod error;
+mod iter;
+mod kind;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod modules;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod numa_maps;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod pagemap;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod procfs;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod query;
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+mod smaps;
+mod symbols;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod threads;
+mod vm_flags;
+
+pub use self::address::read_program_headers;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::build_id::get_process_maps_with_build_ids;
+pub use self::build_id::BuildId;
+pub use self::core_dump::{get_core_dump_maps, parse_core_dump};
+pub use self::device::{DeviceNumber, MountInfo};
+pub use self::error::{ParseError, ParseField};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::iter::MapsReader;
+pub use self::iter::{MapRangeRef, MapsIter};
+pub use self::kind::RegionKind;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::modules::{get_process_modules, Module};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::numa_maps::{get_process_numa_maps, NumaMapping, NumaPolicy, NumaPolicyMode};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::pagemap::{Pagemap, PagemapEntry, ResidencyBitmap};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::procfs::ProcFs;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::query::{query_address, MapsQuery};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::smaps::{get_process_smaps, get_process_smaps_rollup};
+pub use self::smaps::{MemoryTotals, MemoryUsage};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::symbols::SymbolResolver;
+pub use self::symbols::{Symbol, SymbolTable};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::threads::{get_thread_maps, get_thread_stacks};
+pub use self::vm_flags::VmFlags;
+pub use elf::ProgramHeader;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub type Pid = libc::pid_t;
+
+/// A struct representing a single virtual memory region.
+///
+/// While this structure is only for Linux, the macOS, Windows, and FreeBSD
+/// variants have identical exposed methods. It is also available on those
+/// targets, so that maps files captured on Linux can be loaded with
+/// [`parse_maps`](fn.parse_maps.html) anywhere.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MapRange {
+    range_start: usize,
+    range_end: usize,
+    pub offset: usize,
+    pub dev: DeviceNumber,
+    pub perms: Permissions,
+    pub inode: usize,
+    pathname: Option<PathBuf>,
+    deleted: bool,
+    memory_usage: Option<MemoryUsage>,
+    vm_flags: Option<VmFlags>,
+    build_id: Option<BuildId>,
+}
+
+impl MapRange {
+    /// Returns the memory accounting for this region, if it was read from
+    /// `/proc/PID/smaps` by [`get_process_smaps`](fn.get_process_smaps.html)
+    pub fn memory_usage(&self) -> Option<&MemoryUsage> {
+        self.memory_usage.as_ref()
+    }
+    /// Returns the kernel's flags for this region, if it was read from
+    /// `/proc/PID/smaps` by [`get_process_smaps`](fn.get_process_smaps.html)
+    pub fn vm_flags(&self) -> Option<&VmFlags> {
+        self.vm_flags.as_ref()
+    }
+    /// Returns the GNU build ID of the ELF file mapped by this region, if it
+    /// was read by
+    /// [`get_process_maps_with_build_ids`](fn.get_process_maps_with_build_ids.html)
+    /// or [`get_process_modules`](fn.get_process_modules.html)
+    pub fn build_id(&self) -> Option<&BuildId> {
+        self.build_id.as_ref()
+    }
+    /// Returns what kind of memory this region holds, based on its pathname
+    pub fn kind(&self) -> RegionKind {
+        RegionKind::from_pathname(self.pathname.as_deref(), self.deleted)
+    }
+    /// Returns whether the file backing this region has been deleted or
+    /// replaced since it was mapped. The kernel's ` (deleted)` marker is
+    /// stripped from [`filename`](#method.filename), so the backing file can
+    /// only be opened with [`open_map_file`](fn.open_map_file.html).
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+    /// Replaces the pathname of this region, for example to redact it before
+    /// writing the range back out with `Display`
+    pub fn set_filename(&mut self, filename: Option<PathBuf>) {
+        self.pathname = filename;
+    }
+    /// Writes this range as a line of `/proc/PID/maps` without the trailing
+    /// newline, in the same column layout as the kernel, so that it parses
+    /// back to an equal MapRange. Unlike `Display`, this writes the bytes of
+    /// the pathname as they are, even if they aren't valid UTF-8.
+    pub fn write_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        let header = format!(
+            "{:08x}-{:08x} {} {:08x} {} {} ",
+            self.range_start, self.range_end, self.perms, self.offset, self.dev, self.inode
+        );
+        w.write_all(header.as_bytes())?;
+        if let Some(ref pathname) = self.pathname {
+            // The kernel pads the columns before the pathname out to a fixed
+            // width that depends on the pointer size
+            let width = 25 + std::mem::size_of::<usize>() * 6 - 1;
+            for _ in header.len()..width {
+                w.write_all(b" ")?;
+            }
+            w.write_all(b" ")?;
+            for (i, part) in path_bytes(pathname).split(|&b| b == b'\n').enumerate() {
+                if i > 0 {
+                    w.write_all(b"\\012")?;
+                }
+                w.write_all(part)?;
+            }
+            if self.deleted {
+                w.write_all(DELETED_SUFFIX.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MapRange {
+    /// Writes this range like [`write_to`](#method.write_to). Pathnames that
+    /// aren't valid UTF-8 have the invalid bytes replaced with U+FFFD, so
+    /// they no longer name the same file; use `write_to` to keep them.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut line = Vec::new();
+        self.write_to(&mut line).map_err(|_| fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&line))
+    }
+}
+
+impl FromStr for MapRange {
+    type Err = ParseError;
+
+    /// Parses a single line of a `/proc/PID/maps` file
+    fn from_str(s: &str) -> Result<MapRange, ParseError> {
+        Ok(MapRangeRef::parse(s.as_bytes())?.into())
+    }
+}
+
+impl MapRangeImpl for MapRange {
+    fn size(&self) -> usize {
+        self.range_end - self.range_start
+    }
+    fn start(&self) -> usize {
+        self.range_start
+    }
+    fn filename(&self) -> Option<&Path> {
+        self.pathname.as_deref()
+    }
+    fn is_exec(&self) -> bool {
+        self.perms.is_exec()
+    }
+    fn is_write(&self) -> bool {
+        self.perms.is_write()
+    }
+    fn is_read(&self) -> bool {
+        self.perms.is_read()
+    }
+    fn permissions(&self) -> Permissions {
+        self.perms
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Gets a Vec of [`MapRange`](linux_maps/struct.MapRange.html) structs for
+/// the passed in PID. (Note that while this function is for Linux, the macOS,
+/// Windows, and FreeBSD variants have the same interface)
+pub fn get_process_maps(pid: Pid) -> std::io::Result<Vec<MapRange>> {
+    ProcFs::default().get_process_maps(pid)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Gets the maps of the passed in PID like
+/// [`get_process_maps`](fn.get_process_maps.html), but skips lines that fail to
+/// parse instead of failing completely.
+///
+/// Returns every range that parsed, along with a
+/// [`ParseError`](struct.ParseError.html) for each line that was skipped.
+/// Errors reading the file itself are still returned as errors.
+pub fn get_process_maps_lenient(pid: Pid) -> std::io::Result<(Vec<MapRange>, Vec<ParseError>)> {
+    ProcFs::default().get_process_maps_lenient(pid)
+}
+
+/// The marker that the kernel appends to the pathname of a mapped file that
+/// has been deleted
+const DELETED_SUFFIX: &str = " (deleted)";
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Returns the path of the `/proc/PID/map_files` link for the passed in
+/// MapRange
+pub fn map_file_path(pid: Pid, map: &MapRange) -> PathBuf {
+    ProcFs::default().map_file_path(pid, map)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Opens the file backing a MapRange through `/proc/PID/map_files`.
+///
+/// This works even when the file has been deleted or replaced on disk, which
+/// makes it possible to read the binary that is actually mapped. The kernel
+/// requires `CAP_SYS_ADMIN` (or `CAP_CHECKPOINT_RESTORE`) to open these links.
+pub fn open_map_file(pid: Pid, map: &MapRange) -> std::io::Result<File> {
+    ProcFs::default().open_map_file(pid, map)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl ProcFs {
+    /// Gets the maps of the passed in PID from this procfs, like
+    /// [`get_process_maps`](fn.get_process_maps.html)
+    pub fn get_process_maps(&self, pid: Pid) -> std::io::Result<Vec<MapRange>> {
+        // Parses /proc/PID/maps into a Vec<MapRange>
+        let mut contents = Vec::new();
+        self.read_bytes(pid, "maps", &mut contents)?;
+        parse_proc_maps(&contents)
+    }
+
+    /// Gets the maps of the passed in PID from this procfs, skipping lines
+    /// that fail to parse, like
+    /// [`get_process_maps_lenient`](fn.get_process_maps_lenient.html)
+    pub fn get_process_maps_lenient(
+        &self,
+        pid: Pid,
+    ) -> std::io::Result<(Vec<MapRange>, Vec<ParseError>)> {
+        let mut contents = Vec::new();
+        self.read_bytes(pid, "maps", &mut contents)?;
+        Ok(parse_maps_lenient(&contents))
+    }
+
+    /// Returns the path of the `map_files` link for the passed in MapRange in
+    /// this procfs
+    pub fn map_file_path(&self, pid: Pid, map: &MapRange) -> PathBuf {
+        self.path(
+            pid,
+            &format!("map_files/{:x}-{:x}", map.range_start, map.range_end),
+        )
+    }
+
+    /// Opens the file backing a MapRange through this procfs, like
+    /// [`open_map_file`](fn.open_map_file.html)
+    pub fn open_map_file(&self, pid: Pid, map: &MapRange) -> std::io::Result<File> {
+        File::open(self.map_file_path(pid, map))
+    }
+}
+
+/// Decodes the octal escapes (such as `\040` for a space) that the kernel uses
+/// for special characters in file paths
+fn unescape_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let digits = &bytes[i + 1..i + 4];
+            if digits.iter().all(|b| (b'0'..=b'7').contains(b)) {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |acc, b| acc * 8 + (b - b'0') as u32);
+                if value <= 0xff {
+                    out.push(value as u8);
+                    i += 4;
+                    continue;
+                }
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Returns the bytes of a path, as they would appear in `/proc/PID/maps`
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    std::borrow::Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        std::borrow::Cow::Borrowed(s) => std::borrow::Cow::Borrowed(s.as_bytes()),
+        std::borrow::Cow::Owned(s) => std::borrow::Cow::Owned(s.into_bytes()),
+    }
+}
+
+/// Decodes the octal escapes in a path into an OsString, keeping any bytes
+/// that aren't valid UTF-8
+#[cfg(unix)]
+fn unescape_os_string(bytes: &[u8]) -> std::ffi::OsString {
+    use std::os::unix::ffi::OsStringExt;
+    std::ffi::OsString::from_vec(unescape_bytes(bytes))
+}
+
+// Only unix paths can hold arbitrary bytes, so anything else is replaced
+#[cfg(not(unix))]
+fn unescape_os_string(bytes: &[u8]) -> std::ffi::OsString {
+    String::from_utf8_lossy(&unescape_bytes(bytes))
+        .into_owned()
+        .into()
+}
+
+fn parse_proc_maps(contents: &[u8]) -> std::io::Result<Vec<MapRange>> {
+    MapsIter::new(contents)
+        .map(|range| Ok(range?.into()))
+        .collect()
+}
+
+/// Parses the contents of a `/proc/PID/maps` file, such as one that was saved
+/// from another machine.
+///
+/// Parsing stops at the first empty line, and fails on the first line that
+/// can't be parsed.
+pub fn parse_maps(contents: &str) -> Result<Vec<MapRange>, ParseError> {
+    MapsIter::new(contents.as_bytes())
+        .map(|range| range.map(MapRange::from))
+        .collect()
+}
+
+/// Reads and parses a `/proc/PID/maps` file from `reader`.
+///
+/// Unlike [`parse_maps`](fn.parse_maps.html) this accepts pathnames that
+/// aren't valid UTF-8, on the platforms where paths can hold them.
+pub fn parse_maps_from_reader<R: Read>(mut reader: R) -> std::io::Result<Vec<MapRange>> {
+    let mut contents = Vec::new();
+    reader.read_to_end(&mut contents)?;
+    parse_proc_maps(&contents)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_maps_lenient(contents: &[u8]) -> (Vec<MapRange>, Vec<ParseError>) {
+    let mut ranges = Vec::new();
+    let mut skipped = Vec::new();
+    for range in MapsIter::new(contents) {
+        match range {
+            Ok(range) => ranges.push(range.into()),
+            Err(e) => skipped.push(e),
+        }
+    }
+    (ranges, skipped)
+}
+
+#[test]
+fn test_parse_maps() {
+    let contents = include_bytes!("../../ci/testdata/map.txt");
+    let vec = parse_proc_maps(contents).unwrap();
+    let expected = vec![
+        MapRange {
+            range_start: 0x00400000,
+            range_end: 0x00507000,
+            offset: 0,
+            dev: "00:14".parse().unwrap(),
+            perms: "r-xp".parse().unwrap(),
+            inode: 205736,
+            pathname: Some(PathBuf::from("/usr/bin/fish")),
+            deleted: false,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        },
+        MapRange {
+            range_start: 0x00708000,
+            range_end: 0x0070a000,
+            offset: 0,
+            dev: "00:00".parse().unwrap(),
+            perms: "rw-p".parse().unwrap(),
+            inode: 0,
+            pathname: None,
+            deleted: false,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        },
+        MapRange {
+            range_start: 0x0178c000,
+            range_end: 0x01849000,
+            offset: 0,
+            dev: "00:00".parse().unwrap(),
+            perms: "rw-p".parse().unwrap(),
+            inode: 0,
+            pathname: Some(PathBuf::from("[heap]")),
+            deleted: false,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        },
+        MapRange {
+            range_start: 0x7f438050,
+            range_end: 0x7f438060,
+            offset: 0,
+            dev: "fd:01".parse().unwrap(),
+            perms: "r--p".parse().unwrap(),
+            inode: 59034409,
+            pathname: Some(PathBuf::from(
+                "/usr/lib/x86_64-linux-gnu/libgmodule-2.0.so.0.4200.6",
+            )),
+            deleted: true,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        },
+    ];
+    assert_eq!(vec, expected);
+
+    // Also check that maps_contain_addr works as expected
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        assert!(super::maps_contain_addr(0x00400000, &vec));
+        assert!(!super::maps_contain_addr(0x00300000, &vec));
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_contains_addr_range() {
+    let vec = vec![
+        MapRange {
+            range_start: 0x00400000,
+            range_end: 0x00500000,
+            offset: 0,
+            dev: "00:14".parse().unwrap(),
+            perms: "r-xp".parse().unwrap(),
+            inode: 205736,
+            pathname: Some(PathBuf::from("/usr/bin/fish")),
+            deleted: false,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        },
+        MapRange {
+            range_start: 0x00600000,
+            range_end: 0x00700000,
+            offset: 0,
+            dev: "00:14".parse().unwrap(),
+            perms: "r--p".parse().unwrap(),
+            inode: 205736,
+            pathname: Some(PathBuf::from("/usr/bin/fish")),
+            deleted: false,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        },
+        MapRange {
+            range_start: 0x00700000,
+            range_end: 0x00800000,
+            offset: 0,
+            dev: "00:14".parse().unwrap(),
+            perms: "r--p".parse().unwrap(),
+            inode: 205736,
+            pathname: Some(PathBuf::from("/usr/bin/fish")),
+            deleted: false,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        },
+    ];
+
+    assert!(super::maps_contain_addr_range(0x00400000, 0x1, &vec));
+    assert!(super::maps_contain_addr_range(0x00400000, 0x100000, &vec));
+    assert!(super::maps_contain_addr_range(0x00500000 - 1, 1, &vec));
+    assert!(super::maps_contain_addr_range(0x00600000, 0x100001, &vec));
+    assert!(super::maps_contain_addr_range(0x00600000, 0x200000, &vec));
+
+    assert!(!super::maps_contain_addr_range(0x00400000, 0x100001, &vec));
+    assert!(!super::maps_contain_addr_range(
+        0x00400000,
+        usize::MAX,
+        &vec
+    ));
+    assert!(!super::maps_contain_addr_range(0x00400000, 0, &vec));
+    assert!(!super::maps_contain_addr_range(
+        0x00400000, 0x00200000, &vec
+    ));
+    assert!(!super::maps_contain_addr_range(
+        0x00400000, 0x00200001, &vec
+    ));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_open_deleted_map_file() {
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+
+    let path = std::env::temp_dir().join(format!("proc-maps-deleted-{}", std::process::id()));
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"deleted but still mapped").unwrap();
+    let addr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            4096,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    assert_ne!(addr, libc::MAP_FAILED);
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+
+    let pid = std::process::id() as Pid;
+    let maps = get_process_maps(pid).unwrap();
+    let map = maps.iter().find(|m| m.start() == addr as usize).unwrap();
+    assert!(map.is_deleted());
+    assert_eq!(map.filename(), Some(path.as_path()));
+    assert_eq!(map.kind(), RegionKind::DeletedFile);
+
+    let mut contents = String::new();
+    let result = open_map_file(pid, map).and_then(|mut f| f.read_to_string(&mut contents));
+    unsafe { libc::munmap(addr, 4096) };
+    match result {
+        Ok(_) => assert_eq!(contents, "deleted but still mapped"),
+        // Opening map_files needs CAP_SYS_ADMIN
+        Err(ref e) if e.kind() == std::io::ErrorKind::PermissionDenied => {}
+        Err(e) => panic!("{}", e),
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    let contents = "00400000-00507000 r-xp 00000000 00:14 205736 /usr/bin/fish
+00708000-0070a000 rw-p 00000800 00:00 0
+";
+    let err = parse_proc_maps(contents.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    let err = err.get_ref().unwrap().downcast_ref::<ParseError>().unwrap();
+    assert_eq!(err.line(), 2);
+    assert_eq!(err.field(), ParseField::Offset);
+    assert_eq!(err.text(), "00000800");
+    assert_eq!(err.to_string(), "invalid offset \"00000800\" on line 2");
+
+    let cases = [
+        ("00507000-00400000 r-xp 00000000 00:14 0", ParseField::Range),
+        ("00400000 r-xp 00000000 00:14 0", ParseField::Range),
+        ("00400000-00507000 rxp 00000000 00:14 0", ParseField::Perms),
+        (
+            "00400000-00507000 r-xp 0000zz00 00:14 0",
+            ParseField::Offset,
+        ),
+        ("00400000-00507000 r-xp 00000000 0014 0", ParseField::Dev),
+        (
+            "00400000-00507000 r-xp 00000000 00:14 -1",
+            ParseField::Inode,
+        ),
+        ("00400000-00507000 r-xp 00000000", ParseField::Dev),
+    ];
+    for &(line, field) in cases.iter() {
+        let err = MapRangeRef::parse(line.as_bytes()).unwrap_err();
+        assert_eq!(err.field(), field, "{}", line);
+        assert_eq!(err.line(), 1);
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_parse_maps_lenient() {
+    let contents = b"00400000-00507000 r-xp 00000000 00:14 205736 /usr/bin/fish
+00507000-00508000 r--p 00000800 00:14 205736 /usr/bin/fish
+00708000-0070a000 rw-p 00000000 00:00 0
+0178c000-01849000 rw-p 00000000 00:00 0                                  [heap]
+7f438050-7f438060 r-xp 00000000 fd";
+    assert!(parse_proc_maps(contents).is_err());
+
+    let (ranges, skipped) = parse_maps_lenient(contents);
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(ranges[0].filename(), Some(Path::new("/usr/bin/fish")));
+    assert_eq!(ranges[2].kind(), RegionKind::Heap);
+    assert_eq!(skipped.len(), 2);
+    assert_eq!(skipped[0].line(), 2);
+    assert_eq!(skipped[0].field(), ParseField::Offset);
+    assert_eq!(skipped[1].line(), 5);
+    assert_eq!(skipped[1].field(), ParseField::Dev);
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_get_process_maps_lenient() {
+    let (ranges, skipped) = get_process_maps_lenient(std::process::id() as Pid).unwrap();
+    assert!(!ranges.is_empty());
+    assert!(skipped.is_empty());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_unusual_pathnames() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::AsRawFd;
+
+    let dir = std::env::temp_dir();
+    let names: [&[u8]; 4] = [
+        b"proc-maps-\xff\xfe-invalid-utf8",
+        b"proc-maps-two  spaces",
+        b"proc-maps-trailing-space ",
+        b"proc-maps-new\nline",
+    ];
+    let pid = std::process::id() as Pid;
+    for name in names.iter() {
+        let mut name = name.to_vec();
+        name.extend(format!("-{}", pid).bytes());
+        let path = dir.join(OsStr::from_bytes(&name));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(4096).unwrap();
+        let addr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                4096,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        assert_ne!(addr, libc::MAP_FAILED);
+
+        let maps = get_process_maps(pid);
+        let smaps = get_process_smaps(pid);
+        unsafe { libc::munmap(addr, 4096) };
+        std::fs::remove_file(&path).unwrap();
+
+        for maps in [maps.unwrap(), smaps.unwrap()].iter() {
+            let map = maps.iter().find(|m| m.start() == addr as usize).unwrap();
+            assert_eq!(map.filename(), Some(path.as_path()));
+            assert!(!map.is_deleted());
+        }
+    }
+}
+
+#[test]
+fn test_parse_maps_public() {
+    let contents = include_str!("../../ci/testdata/map.txt");
+    let vec = parse_maps(contents).unwrap();
+    assert_eq!(vec, parse_proc_maps(contents.as_bytes()).unwrap());
+    assert_eq!(
+        vec,
+        parse_maps_from_reader(std::io::Cursor::new(contents)).unwrap()
+    );
+
+    let line = contents.lines().next().unwrap();
+    let map: MapRange = line.parse().unwrap();
+    assert_eq!(map, vec[0]);
+    assert_eq!(map.start(), 0x00400000);
+    assert_eq!(map.filename(), Some(Path::new("/usr/bin/fish")));
+
+    let err = "00400000-00507000 r-xp 00000000 00:14"
+        .parse::<MapRange>()
+        .unwrap_err();
+    assert_eq!(err.field(), ParseField::Inode);
+    let err = parse_maps("00400000-00507000 r-xp 00000000 00:14 0\nbad\n").unwrap_err();
+    assert_eq!(err.line(), 2);
+    assert_eq!(err.field(), ParseField::Range);
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_display_round_trip() {
+    let contents = include_str!("../../ci/testdata/map_canonical.txt");
+    let mut vec = parse_maps(contents).unwrap();
+    let written: String = vec.iter().map(|map| format!("{}\n", map)).collect();
+    assert_eq!(written, contents);
+    assert_eq!(parse_maps(&written).unwrap(), vec);
+
+    let newline = vec
+        .iter()
+        .find(|m| m.perms.is_shared() && m.inode == 1220787);
+    assert_eq!(
+        newline.unwrap().filename(),
+        Some(Path::new("/home/alice/data/new\nline.bin"))
+    );
+
+    // Redacting a pathname keeps the rest of the line in place
+    for map in vec.iter_mut() {
+        let redacted = map
+            .filename()
+            .and_then(|f| f.strip_prefix("/home/alice").ok())
+            .map(|f| Path::new("/home/user").join(f));
+        if redacted.is_some() {
+            map.set_filename(redacted);
+        }
+    }
+    assert_eq!(
+        vec[15].to_string(),
+        "7ff9521bb000-7ff9521bc000 rw-p 00000000 fe:00 1220788                    /home/user/data/deleted.bin (deleted)"
+    );
+    assert_eq!(
+        vec[4].to_string(),
+        "7ff9513f6000-7ff951529000 rw-p 00000000 00:00 0 "
+    );
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+#[test]
+fn test_write_to_non_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let line: &[u8] =
+        b"7f0000000000-7f0000001000 r--p 00000000 fe:00 42                         /tmp/caf\xe9\\012x (deleted)";
+    let maps = parse_proc_maps(line).unwrap();
+    assert_eq!(
+        maps[0].filename().unwrap().as_os_str().as_bytes(),
+        b"/tmp/caf\xe9\nx"
+    );
+
+    // write_to keeps the bytes of the pathname, so the line parses back to
+    // the same file
+    let mut written = Vec::new();
+    maps[0].write_to(&mut written).unwrap();
+    assert_eq!(written, line);
+    written.push(b'\n');
+    assert_eq!(parse_maps_from_reader(&written[..]).unwrap(), maps);
+
+    // Display can only replace the invalid byte
+    let displayed = maps[0].to_string();
+    assert!(displayed.ends_with("/tmp/caf\u{fffd}\\012x (deleted)"));
+    assert_ne!(parse_maps(&displayed).unwrap(), maps);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    use serde_json;
+
+    let vec = smaps::parse_smaps(include_bytes!("../../ci/testdata/smaps.txt")).unwrap();
+    let json = serde_json::to_string(&vec).unwrap();
+    let parsed: Vec<MapRange> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, vec);
+
+    // The field names and formats are part of the documented schema
+    let value = serde_json::to_value(&vec[0]).unwrap();
+    assert_eq!(value["range_start"], 0x00400000);
+    assert_eq!(value["range_end"], 0x00507000);
+    assert_eq!(value["offset"], 0);
+    assert_eq!(value["dev"], serde_json::json!({"major": 0, "minor": 0x14}));
+    assert_eq!(
+        value["perms"],
+        serde_json::json!({"read": true, "write": false, "exec": true, "shared": false})
+    );
+    assert_eq!(value["inode"], 205736);
+    assert_eq!(value["pathname"], "/usr/bin/fish");
+    assert_eq!(value["deleted"], false);
+    assert_eq!(value["memory_usage"]["rss"], 920 * 1024);
+    assert_eq!(value["vm_flags"], "rd ex mr mw me dw sd");
+    assert_eq!(value["build_id"], serde_json::Value::Null);
+
+    let mut with_id = vec[0].clone();
+    with_id.build_id = Some(BuildId::new(vec![0xab, 0x01]));
+    let value = serde_json::to_value(&with_id).unwrap();
+    assert_eq!(value["build_id"], "ab01");
+    assert_eq!(serde_json::from_value::<MapRange>(value).unwrap(), with_id);
+
+    let kind = serde_json::to_value(RegionKind::ThreadStack(42)).unwrap();
+    assert_eq!(kind, serde_json::json!({"ThreadStack": 42}));
+}
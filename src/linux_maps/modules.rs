@@ -0,0 +1,335 @@
+use libc;
+use std;
+use std::fs::File;
+use std::io::BufReader;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use super::{read_program_headers, BuildId, MapRange, Pid, ProcFs, ProgramHeader, RegionKind};
+use elf;
+
+/// A file that is loaded into a process, such as the executable or a shared
+/// library, made up of consecutive mappings of that file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Module {
+    pathname: PathBuf,
+    base: usize,
+    end: usize,
+    segments: Vec<MapRange>,
+    load_bias: Option<usize>,
+    program_headers: Vec<ProgramHeader>,
+    build_id: Option<BuildId>,
+}
+
+impl Module {
+    /// Returns the path of the loaded file
+    pub fn filename(&self) -> &Path {
+        &self.pathname
+    }
+    /// Returns the lowest address of any of the module's mappings
+    pub fn base(&self) -> usize {
+        self.base
+    }
+    /// Returns the address just past the end of the module's last mapping
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    /// Returns the size of the address range that the module covers
+    pub fn size(&self) -> usize {
+        self.end - self.base
+    }
+    /// Returns whether an address falls within the module
+    pub fn contains(&self, addr: usize) -> bool {
+        self.base <= addr && addr < self.end
+    }
+    /// Returns the mappings that make up the module, in address order
+    pub fn segments(&self) -> &[MapRange] {
+        &self.segments
+    }
+    /// Returns the ELF load bias, which is added to the virtual addresses in
+    /// the file's program headers and symbol tables to get the addresses in
+    /// the process. This is the base address for position independent code
+    /// and zero for executables that are loaded at a fixed address.
+    ///
+    /// This is `None` for files that aren't ELF files, or that couldn't be
+    /// read.
+    pub fn load_bias(&self) -> Option<usize> {
+        self.load_bias
+    }
+    /// Returns the program headers of the module's ELF file, for converting
+    /// between addresses in its segments and ELF virtual addresses with
+    /// [`MapRange::to_elf_vaddr`](struct.MapRange.html#method.to_elf_vaddr).
+    /// This is empty if the file couldn't be read.
+    pub fn program_headers(&self) -> &[ProgramHeader] {
+        &self.program_headers
+    }
+    /// Returns the GNU build ID from the module's `NT_GNU_BUILD_ID` note.
+    ///
+    /// This is `None` for files without the note, and for files that aren't
+    /// ELF files or that couldn't be read.
+    pub fn build_id(&self) -> Option<&BuildId> {
+        self.build_id.as_ref()
+    }
+}
+
+/// Gets the loaded modules of the passed in PID, by grouping consecutive
+/// mappings of the same file from [`get_process_maps`](fn.get_process_maps.html).
+///
+/// The load bias is worked out from the program headers of the mapped file,
+/// which is opened through `/proc/PID/map_files` if possible, so that
+/// deleted and replaced files can still be read. Otherwise a file that hasn't
+/// been deleted is opened through `/proc/PID/root`, and finally by its
+/// pathname, as long as that still has the device and inode of the mapping.
+///
+/// The build ID is read from the headers that are loaded in the process's
+/// memory through `/proc/PID/mem`, so that it matches the code that is
+/// running even if the file was replaced. If the memory can't be read, it is
+/// read from the file instead. The build ID is attached to the module's
+/// segments too.
+pub fn get_process_modules(pid: Pid) -> std::io::Result<Vec<Module>> {
+    ProcFs::default().get_process_modules(pid)
+}
+
+impl ProcFs {
+    /// Gets the loaded modules of the passed in PID from this procfs, like
+    /// [`get_process_modules`](fn.get_process_modules.html)
+    pub fn get_process_modules(&self, pid: Pid) -> std::io::Result<Vec<Module>> {
+        Ok(self.modules_from_maps(pid, self.get_process_maps(pid)?))
+    }
+
+    /// Groups the passed in PID's maps into modules, and reads the load bias
+    /// and build ID of each
+    pub(super) fn modules_from_maps(&self, pid: Pid, maps: Vec<MapRange>) -> Vec<Module> {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as u64 };
+        // Reading another process's memory needs the same access as ptrace,
+        // so this is allowed to fail
+        let mem = File::open(self.path(pid, "mem")).ok();
+        let mut modules = group_modules(maps);
+        for module in modules.iter_mut() {
+            // Segments can share a page of the file, so the one with the
+            // lowest offset gives the most reliable answer
+            let first = match module.segments.iter().min_by_key(|m| m.offset) {
+                Some(first) => first,
+                None => continue,
+            };
+            let headers = match self.open_module_file(pid, first) {
+                Some(file) => read_program_headers(BufReader::new(file)).unwrap_or_default(),
+                None => Vec::new(),
+            };
+            module.load_bias = elf::load_bias(
+                &headers,
+                first.start() as u64,
+                first.offset as u64,
+                page_size,
+            )
+            .map(|bias| bias as usize);
+            module.program_headers = headers;
+            module.build_id = self.read_build_id(pid, mem.as_ref(), first, page_size);
+            for segment in module.segments.iter_mut() {
+                segment.build_id = module.build_id.clone();
+            }
+        }
+        modules
+    }
+
+    /// Opens the file behind a mapping, returning `None` if it can't be opened.
+    ///
+    /// The pathname of a deleted file could since have been reused, and any
+    /// pathname can have been replaced with another file, so the fallbacks
+    /// to the pathname are only used for files that aren't deleted, and only
+    /// if they open the same device and inode as the mapping.
+    pub(super) fn open_module_file(&self, pid: Pid, map: &MapRange) -> Option<File> {
+        if let Ok(file) = self.open_map_file(pid, map) {
+            return Some(file);
+        }
+        if map.is_deleted() {
+            return None;
+        }
+        let path = map.filename()?;
+        let rooted = self.path(pid, "root").join(path.strip_prefix("/").ok()?);
+        open_if_mapped(&rooted, map).or_else(|| open_if_mapped(path, map))
+    }
+}
+
+/// Opens a file, returning `None` if it isn't the file that `map` maps
+fn open_if_mapped(path: &Path, map: &MapRange) -> Option<File> {
+    let file = File::open(path).ok()?;
+    let meta = file.metadata().ok()?;
+    if map.dev.matches_metadata(&meta) && meta.ino() == map.inode as u64 {
+        Some(file)
+    } else {
+        None
+    }
+}
+
+/// Groups consecutive mappings of the same file into modules, without their
+/// load biases or build IDs
+fn group_modules(maps: Vec<MapRange>) -> Vec<Module> {
+    let mut modules: Vec<Module> = Vec::new();
+    for map in maps {
+        // The loader always maps files privately, while shared mappings hold
+        // data, including shared anonymous memory that shows up as a deleted
+        // `/dev/zero`
+        match map.kind() {
+            RegionKind::File | RegionKind::DeletedFile if map.perms.is_private() => {}
+            _ => continue,
+        }
+        if let Some(module) = modules.last_mut() {
+            let last = module.segments.last().unwrap();
+            if last.filename() == map.filename()
+                && last.dev == map.dev
+                && last.inode == map.inode
+                && last.is_deleted() == map.is_deleted()
+            {
+                module.base = module.base.min(map.start());
+                module.end = module.end.max(map.start() + map.size());
+                module.segments.push(map);
+                continue;
+            }
+        }
+        modules.push(Module {
+            pathname: map.filename().unwrap().to_path_buf(),
+            base: map.start(),
+            end: map.start() + map.size(),
+            segments: vec![map],
+            load_bias: None,
+            program_headers: Vec::new(),
+            build_id: None,
+        });
+    }
+    modules
+}
+
+#[test]
+fn test_group_modules() {
+    let maps = super::parse_maps(include_str!("../../ci/testdata/map_canonical.txt")).unwrap();
+    let modules = group_modules(maps);
+    let summary: Vec<(&Path, usize, usize, usize)> = modules
+        .iter()
+        .map(|m| (m.filename(), m.base(), m.size(), m.segments().len()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (Path::new("/usr/bin/python3.11"), 0x55cc1d10d000, 0x3000, 3),
+            (
+                Path::new("/usr/lib/x86_64-linux-gnu/libc.so.6"),
+                0x7ff951a1e000,
+                0x1d5000,
+                5
+            ),
+            (
+                Path::new("/home/alice/data/deleted.bin"),
+                0x7ff9521bb000,
+                0x1000,
+                1
+            ),
+            (
+                Path::new("/home/alice/data/two  spaces.bin"),
+                0x7ff95223b000,
+                0x1000,
+                1
+            ),
+        ]
+    );
+    assert!(modules.iter().all(|m| m.load_bias().is_none()));
+}
+
+#[test]
+fn test_open_module_file() {
+    let pid = std::process::id() as Pid;
+    let exe = std::env::current_exe().unwrap();
+    let maps = super::get_process_maps(pid).unwrap();
+    let map = maps
+        .iter()
+        .find(|m| m.filename() == Some(exe.as_path()))
+        .unwrap();
+
+    // Without map_files or a root, the file is opened by its pathname
+    let procfs = ProcFs::new(std::env::temp_dir().join("proc-maps-missing-procfs"));
+    assert!(procfs.open_module_file(pid, map).is_some());
+
+    // but not if that is a different file from the one that was mapped
+    let mut replaced = map.clone();
+    replaced.inode += 1;
+    assert!(procfs.open_module_file(pid, &replaced).is_none());
+    let mut moved = map.clone();
+    moved.dev.minor += 1;
+    assert!(procfs.open_module_file(pid, &moved).is_none());
+    let mut deleted = map.clone();
+    deleted.deleted = true;
+    assert!(procfs.open_module_file(pid, &deleted).is_none());
+}
+
+#[test]
+fn test_get_process_modules() {
+    let modules = get_process_modules(std::process::id() as Pid).unwrap();
+    let addr = test_get_process_modules as *const () as usize;
+    let exe = modules.iter().find(|m| m.contains(addr)).unwrap();
+    assert_eq!(
+        Some(exe.filename()),
+        std::env::current_exe().ok().as_deref()
+    );
+    assert!(exe.segments().len() > 1);
+    assert!(exe.segments().iter().any(|s| s.is_exec()));
+    assert_eq!(exe.size(), exe.end() - exe.base());
+
+    // The first mapping of an ELF file is its headers, which are at virtual
+    // address zero for position independent executables
+    let bias = exe.load_bias().unwrap();
+    assert!(bias == exe.base() || bias == 0);
+
+    // Every address in the executable is its ELF virtual address plus the bias
+    let text = exe
+        .segments()
+        .iter()
+        .find(|s| s.to_file_offset(addr).is_some())
+        .unwrap();
+    let vaddr = text.to_elf_vaddr(addr, exe.program_headers()).unwrap();
+    assert_eq!(vaddr, addr - bias);
+    assert_eq!(
+        text.elf_vaddr_to_addr(vaddr, exe.program_headers()),
+        Some(addr)
+    );
+
+    // The dynamic loader reports the load bias of each shared library too
+    extern "C" fn collect(
+        info: *mut libc::dl_phdr_info,
+        _: libc::size_t,
+        data: *mut libc::c_void,
+    ) -> libc::c_int {
+        let loaded = unsafe { &mut *(data as *mut Vec<(PathBuf, usize)>) };
+        let info = unsafe { &*info };
+        if !info.dlpi_name.is_null() {
+            let name = unsafe { std::ffi::CStr::from_ptr(info.dlpi_name) };
+            let name = PathBuf::from(name.to_string_lossy().into_owned());
+            loaded.push((name, info.dlpi_addr as usize));
+        }
+        0
+    }
+    let mut loaded: Vec<(PathBuf, usize)> = Vec::new();
+    unsafe {
+        libc::dl_iterate_phdr(
+            Some(collect),
+            &mut loaded as *mut Vec<(PathBuf, usize)> as *mut libc::c_void,
+        )
+    };
+    let mut checked = 0;
+    for (name, addr) in loaded {
+        let canonical = match std::fs::canonicalize(&name) {
+            Ok(canonical) => canonical,
+            Err(_) => continue,
+        };
+        // Files can also be mapped to be read, such as by the backtrace code
+        // in std when another test panics, so only loaded code is compared
+        let module = modules
+            .iter()
+            .find(|m| m.filename() == canonical && m.segments().iter().any(|s| s.is_exec()));
+        if let Some(module) = module {
+            assert_eq!(module.load_bias(), Some(addr), "{:?}", name);
+            checked += 1;
+        }
+    }
+    assert!(checked > 0);
+}
@@ -0,0 +1,374 @@
+use std;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+
+use super::{
+    parse_proc_maps, unescape_bytes, MapRange, ParseError, ParseField, Pid, ProcFs, DELETED_SUFFIX,
+};
+
+/// The NUMA memory policy mode of a mapping, see `set_mempolicy(2)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NumaPolicyMode {
+    Default,
+    Local,
+    Prefer,
+    PreferredMany,
+    Bind,
+    Interleave,
+    WeightedInterleave,
+    /// A mode that this crate doesn't know about yet
+    Unknown(String),
+}
+
+/// The NUMA memory policy of a mapping, as shown in `/proc/PID/numa_maps`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NumaPolicy {
+    pub mode: NumaPolicyMode,
+    /// The nodes that the policy applies to, empty for policies without a
+    /// node list such as `default`
+    pub nodes: Vec<u32>,
+    /// Set for policies created with `MPOL_F_STATIC_NODES`
+    pub static_nodes: bool,
+    /// Set for policies created with `MPOL_F_RELATIVE_NODES`
+    pub relative_nodes: bool,
+}
+
+/// A single line of `/proc/PID/numa_maps`.
+///
+/// Page counts are in units of `kernel_page_size`, and fields that the
+/// kernel omitted for this mapping are `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NumaMapping {
+    pub start: usize,
+    pub policy: NumaPolicy,
+    /// The mapped file, without the ` (deleted)` marker
+    pub file: Option<PathBuf>,
+    /// Whether the mapped file has been deleted or replaced since it was
+    /// mapped, like [`MapRange::is_deleted`](struct.MapRange.html#method.is_deleted)
+    pub deleted: bool,
+    pub heap: bool,
+    pub stack: bool,
+    pub huge: bool,
+    pub anon: Option<usize>,
+    pub dirty: Option<usize>,
+    pub mapped: Option<usize>,
+    pub mapmax: Option<usize>,
+    pub swapcache: Option<usize>,
+    pub active: Option<usize>,
+    pub writeback: Option<usize>,
+    /// The number of pages on each node, as `(node, pages)` pairs sorted by node
+    pub node_pages: Vec<(u32, usize)>,
+    /// The page size of the mapping in bytes
+    pub kernel_page_size: Option<usize>,
+}
+
+/// Gets the NUMA placement of every mapping of the passed in PID, paired with
+/// the matching [`MapRange`](struct.MapRange.html) from `/proc/PID/maps`.
+///
+/// The two files are read separately, so mappings that changed in between are
+/// left out.
+pub fn get_process_numa_maps(pid: Pid) -> std::io::Result<Vec<(MapRange, NumaMapping)>> {
+    ProcFs::default().get_process_numa_maps(pid)
+}
+
+impl ProcFs {
+    /// Gets the NUMA placement of every mapping of the passed in PID from this
+    /// procfs, like [`get_process_numa_maps`](fn.get_process_numa_maps.html)
+    pub fn get_process_numa_maps(&self, pid: Pid) -> std::io::Result<Vec<(MapRange, NumaMapping)>> {
+        let mut contents = Vec::new();
+        self.read_bytes(pid, "maps", &mut contents)?;
+        let maps = parse_proc_maps(&contents)?;
+        self.read_bytes(pid, "numa_maps", &mut contents)?;
+        let numa = parse_numa_maps(&contents)?;
+        Ok(join_numa_maps(maps, numa))
+    }
+}
+
+/// Pairs each numa_maps line with the MapRange that starts at the same address
+fn join_numa_maps(maps: Vec<MapRange>, numa: Vec<NumaMapping>) -> Vec<(MapRange, NumaMapping)> {
+    let mut numa = numa.into_iter().peekable();
+    let mut vec = Vec::new();
+    for map in maps {
+        while numa.peek().is_some_and(|n| n.start < map.start()) {
+            numa.next();
+        }
+        if numa.peek().is_some_and(|n| n.start == map.start()) {
+            vec.push((map, numa.next().unwrap()));
+        }
+    }
+    vec
+}
+
+fn parse_node_list(s: &str) -> Option<Vec<u32>> {
+    let mut nodes = Vec::new();
+    for part in s.split(',').filter(|p| !p.is_empty()) {
+        let mut bounds = part.splitn(2, '-').map(|n| n.parse::<u32>());
+        let (first, last) = match (bounds.next(), bounds.next()) {
+            (Some(Ok(first)), None) => (first, first),
+            (Some(Ok(first)), Some(Ok(last))) if first <= last => (first, last),
+            _ => return None,
+        };
+        nodes.extend(first..=last);
+    }
+    Some(nodes)
+}
+
+fn parse_policy(s: &str) -> Result<NumaPolicy, ParseError> {
+    let (name, nodes) = match s.find(':') {
+        Some(i) => match parse_node_list(&s[i + 1..]) {
+            Some(nodes) => (&s[..i], nodes),
+            None => return Err(ParseError::new(ParseField::Policy, s.as_bytes())),
+        },
+        None => (s, Vec::new()),
+    };
+    let mut flags = name.split('=');
+    let mode = match flags.next().unwrap_or("") {
+        "default" => NumaPolicyMode::Default,
+        "local" => NumaPolicyMode::Local,
+        "prefer" => NumaPolicyMode::Prefer,
+        "preferred_many" => NumaPolicyMode::PreferredMany,
+        "bind" => NumaPolicyMode::Bind,
+        "interleave" => NumaPolicyMode::Interleave,
+        "weighted_interleave" => NumaPolicyMode::WeightedInterleave,
+        other => NumaPolicyMode::Unknown(other.to_string()),
+    };
+    // Mode flags are separated by `|`, such as `static|balancing`
+    let flags: Vec<&str> = flags.next().unwrap_or("").split('|').collect();
+    Ok(NumaPolicy {
+        mode,
+        nodes,
+        static_nodes: flags.contains(&"static"),
+        relative_nodes: flags.contains(&"relative"),
+    })
+}
+
+/// Parses the count in a `key=value` field
+fn parse_count(field: &str, value: &str) -> Result<usize, ParseError> {
+    value
+        .parse::<usize>()
+        .map_err(|_| ParseError::new(ParseField::Field, field.as_bytes()))
+}
+
+fn parse_numa_line(line: &[u8]) -> Result<NumaMapping, ParseError> {
+    // Everything but the file path is ASCII, so only it is kept as bytes
+    let mut fields = line.split(|&b| b == b' ').filter(|s| !s.is_empty());
+    let mut file = None;
+    let mut deleted = false;
+    let mut split = Vec::new();
+    for field in &mut fields {
+        if field.starts_with(b"file=") {
+            let mut path = unescape_bytes(&field[5..]);
+            deleted = path.ends_with(DELETED_SUFFIX.as_bytes());
+            if deleted {
+                path.truncate(path.len() - DELETED_SUFFIX.len());
+            }
+            file = Some(PathBuf::from(OsString::from_vec(path)));
+        } else {
+            match std::str::from_utf8(field) {
+                Ok(field) => split.push(field),
+                Err(_) => return Err(ParseError::new(ParseField::Field, field)),
+            }
+        }
+    }
+    let mut split = split.into_iter().peekable();
+    let start = match split.next() {
+        None => return Err(ParseError::new(ParseField::Range, b"")),
+        Some(s) => match usize::from_str_radix(s, 16) {
+            Err(_) => return Err(ParseError::new(ParseField::Range, s.as_bytes())),
+            Ok(i) => i,
+        },
+    };
+    let policy = match split.next() {
+        None => return Err(ParseError::new(ParseField::Policy, b"")),
+        // The weighted interleave mode is printed with a space in its name
+        Some("weighted") => match split.next() {
+            Some(s) if s.starts_with("interleave") => parse_policy(&format!("weighted_{}", s))?,
+            _ => return Err(ParseError::new(ParseField::Policy, b"weighted")),
+        },
+        // As is the preferred many mode, which is `prefer (many)`, followed
+        // by its flags and node list
+        Some("prefer") if split.peek().is_some_and(|s| s.starts_with("(many)")) => {
+            let s = split.next().unwrap();
+            parse_policy(&format!("preferred_many{}", &s["(many)".len()..]))?
+        }
+        Some(s) => parse_policy(s)?,
+    };
+
+    let mut mapping = NumaMapping {
+        start,
+        policy,
+        file,
+        deleted,
+        heap: false,
+        stack: false,
+        huge: false,
+        anon: None,
+        dirty: None,
+        mapped: None,
+        mapmax: None,
+        swapcache: None,
+        active: None,
+        writeback: None,
+        node_pages: Vec::new(),
+        kernel_page_size: None,
+    };
+
+    for field in split {
+        let (key, value) = match field.find('=') {
+            Some(i) => (&field[..i], &field[i + 1..]),
+            None => {
+                match field {
+                    "heap" => mapping.heap = true,
+                    "stack" => mapping.stack = true,
+                    "huge" => mapping.huge = true,
+                    _ => {}
+                }
+                continue;
+            }
+        };
+        match key {
+            "anon" => mapping.anon = Some(parse_count(field, value)?),
+            "dirty" => mapping.dirty = Some(parse_count(field, value)?),
+            "mapped" => mapping.mapped = Some(parse_count(field, value)?),
+            "mapmax" => mapping.mapmax = Some(parse_count(field, value)?),
+            "swapcache" => mapping.swapcache = Some(parse_count(field, value)?),
+            "active" => mapping.active = Some(parse_count(field, value)?),
+            "writeback" => mapping.writeback = Some(parse_count(field, value)?),
+            "kernelpagesize_kB" => {
+                mapping.kernel_page_size = Some(parse_count(field, value)? * 1024)
+            }
+            _ if key.starts_with('N') => {
+                let node = key[1..]
+                    .parse::<u32>()
+                    .map_err(|_| ParseError::new(ParseField::Field, field.as_bytes()))?;
+                mapping.node_pages.push((node, parse_count(field, value)?));
+            }
+            _ => {}
+        }
+    }
+    mapping.node_pages.sort();
+    Ok(mapping)
+}
+
+fn parse_numa_maps(contents: &[u8]) -> std::io::Result<Vec<NumaMapping>> {
+    let mut vec = Vec::new();
+    for (i, line) in contents.split(|&b| b == b'\n').enumerate() {
+        if line.iter().all(|b| b.is_ascii_whitespace()) {
+            break;
+        }
+        vec.push(parse_numa_line(line).map_err(|e| e.at_line(i + 1))?);
+    }
+    Ok(vec)
+}
+
+#[test]
+fn test_parse_numa_maps() {
+    let vec = parse_numa_maps(include_bytes!("../../ci/testdata/numa_maps.txt")).unwrap();
+    assert_eq!(vec.len(), 8);
+
+    assert_eq!(vec[0].start, 0x00400000);
+    assert_eq!(vec[0].policy.mode, NumaPolicyMode::Default);
+    assert!(vec[0].policy.nodes.is_empty());
+    assert_eq!(vec[0].file, Some(PathBuf::from("/usr/bin/fish")));
+    assert!(!vec[0].deleted);
+    assert_eq!(vec[0].mapped, Some(200));
+    assert_eq!(vec[0].mapmax, Some(3));
+    assert_eq!(vec[0].anon, None);
+    assert_eq!(vec[0].node_pages, vec![(0, 120), (1, 80)]);
+    assert_eq!(vec[0].kernel_page_size, Some(4096));
+
+    assert_eq!(vec[1].policy.mode, NumaPolicyMode::Bind);
+    assert_eq!(vec[1].policy.nodes, vec![0, 1]);
+    assert_eq!(vec[1].anon, Some(2));
+    assert_eq!(vec[1].active, Some(1));
+
+    assert_eq!(vec[2].policy.mode, NumaPolicyMode::Interleave);
+    assert_eq!(vec[2].policy.nodes, vec![0, 1, 2, 3]);
+    assert!(vec[2].heap);
+    assert_eq!(vec[2].swapcache, Some(4));
+    assert_eq!(vec[2].node_pages.len(), 4);
+
+    assert_eq!(vec[3].policy.mode, NumaPolicyMode::Prefer);
+    assert_eq!(vec[3].policy.nodes, vec![1]);
+    assert_eq!(
+        vec[3].file,
+        Some(PathBuf::from(
+            "/usr/lib/x86_64-linux-gnu/libgmodule-2.0.so.0.4200.6"
+        ))
+    );
+    assert!(vec[3].deleted);
+
+    assert_eq!(
+        vec[4].policy,
+        NumaPolicy {
+            mode: NumaPolicyMode::Bind,
+            nodes: vec![0, 2],
+            static_nodes: true,
+            relative_nodes: false,
+        }
+    );
+    assert!(vec[4].huge);
+    assert_eq!(vec[4].kernel_page_size, Some(2 * 1024 * 1024));
+
+    assert_eq!(vec[5].policy.mode, NumaPolicyMode::WeightedInterleave);
+    assert_eq!(vec[5].policy.nodes, vec![0, 1]);
+
+    assert_eq!(vec[6].policy.mode, NumaPolicyMode::PreferredMany);
+    assert_eq!(vec[6].policy.nodes, vec![0, 1]);
+    assert_eq!(vec[6].anon, Some(3));
+
+    // Mode flags can be combined
+    assert_eq!(
+        vec[7].policy,
+        NumaPolicy {
+            mode: NumaPolicyMode::Bind,
+            nodes: vec![0, 1],
+            static_nodes: true,
+            relative_nodes: false,
+        }
+    );
+
+    // The lines for the first four mappings match a range in map.txt
+    let maps = parse_proc_maps(include_bytes!("../../ci/testdata/map.txt")).unwrap();
+    let joined = join_numa_maps(maps, vec);
+    assert_eq!(joined.len(), 4);
+    assert!(joined.iter().all(|(map, numa)| map.start() == numa.start));
+    assert_eq!(joined[2].0.filename(), Some(std::path::Path::new("[heap]")));
+}
+
+#[test]
+fn test_parse_numa_maps_errors() {
+    let parse_error = |contents: &[u8]| {
+        let err = parse_numa_maps(contents).unwrap_err();
+        let err = err.get_ref().unwrap().downcast_ref::<ParseError>().unwrap();
+        (err.line(), err.field(), err.text().to_string())
+    };
+    let first = b"00400000 default anon=1\n";
+    assert_eq!(
+        parse_error(&[&first[..], b"0040z000 default\n"].concat()),
+        (2, ParseField::Range, "0040z000".to_string())
+    );
+    assert_eq!(
+        parse_error(&[&first[..], b"00600000 bind:2-0\n"].concat()),
+        (2, ParseField::Policy, "bind:2-0".to_string())
+    );
+    assert_eq!(
+        parse_error(b"00400000 default anon=x\n"),
+        (1, ParseField::Field, "anon=x".to_string())
+    );
+}
+
+#[test]
+fn test_get_process_numa_maps() {
+    let vec = match get_process_numa_maps(std::process::id() as Pid) {
+        Ok(vec) => vec,
+        // Kernels built without CONFIG_NUMA have no numa_maps file
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => panic!("{}", e),
+    };
+    assert!(!vec.is_empty());
+}
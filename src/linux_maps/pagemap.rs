@@ -0,0 +1,329 @@
+use libc;
+use std;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+
+use super::{MapRange, Pid, ProcFs};
+
+const PFN_MASK: u64 = (1 << 55) - 1;
+const SWAP_TYPE_MASK: u64 = 0x1f;
+const SOFT_DIRTY: u64 = 1 << 55;
+const EXCLUSIVE: u64 = 1 << 56;
+const UFFD_WP: u64 = 1 << 57;
+const FILE_OR_SHARED_ANON: u64 = 1 << 61;
+const SWAPPED: u64 = 1 << 62;
+const PRESENT: u64 = 1 << 63;
+
+/// Number of entries read from the pagemap file at a time
+const CHUNK_ENTRIES: usize = 4096;
+
+/// A single page's entry from `/proc/PID/pagemap`.
+///
+/// See the kernel's `Documentation/admin-guide/mm/pagemap.rst` for the
+/// meaning of each bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PagemapEntry {
+    address: usize,
+    raw: u64,
+}
+
+impl PagemapEntry {
+    /// Returns the virtual address of the page this entry describes
+    pub fn address(&self) -> usize {
+        self.address
+    }
+    /// Returns the raw 64 bit value read from the pagemap file
+    pub fn raw(&self) -> u64 {
+        self.raw
+    }
+    /// Returns whether the page is present in RAM
+    pub fn is_present(&self) -> bool {
+        self.raw & PRESENT != 0
+    }
+    /// Returns whether the page has been swapped out
+    pub fn is_swapped(&self) -> bool {
+        self.raw & SWAPPED != 0
+    }
+    /// Returns whether the page is file-backed or shared anonymous memory
+    pub fn is_file_or_shared_anon(&self) -> bool {
+        self.raw & FILE_OR_SHARED_ANON != 0
+    }
+    /// Returns whether the page is mapped exclusively by this process
+    pub fn is_exclusive(&self) -> bool {
+        self.raw & EXCLUSIVE != 0
+    }
+    /// Returns whether the page has been written to since the soft-dirty bits
+    /// were last cleared through `/proc/PID/clear_refs`
+    pub fn is_soft_dirty(&self) -> bool {
+        self.raw & SOFT_DIRTY != 0
+    }
+    /// Returns whether the page is write-protected by userfaultfd
+    pub fn is_uffd_wp(&self) -> bool {
+        self.raw & UFFD_WP != 0
+    }
+    /// Returns the page frame number of a present page.
+    ///
+    /// The kernel only reports PFNs to readers with `CAP_SYS_ADMIN`, and
+    /// zeroes them for everyone else, so this returns `None` in that case.
+    pub fn pfn(&self) -> Option<u64> {
+        if !self.is_present() {
+            return None;
+        }
+        Some(self.raw & PFN_MASK).filter(|&pfn| pfn != 0)
+    }
+    /// Returns the swap type of a swapped out page
+    pub fn swap_type(&self) -> Option<u8> {
+        if !self.is_swapped() {
+            return None;
+        }
+        Some((self.raw & SWAP_TYPE_MASK) as u8)
+    }
+    /// Returns the offset into the swap area of a swapped out page
+    pub fn swap_offset(&self) -> Option<u64> {
+        if !self.is_swapped() {
+            return None;
+        }
+        Some((self.raw & PFN_MASK) >> 5)
+    }
+}
+
+/// A compact summary of which pages in an address range are in RAM or swap,
+/// with one bit per page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResidencyBitmap {
+    start: usize,
+    page_size: usize,
+    pages: usize,
+    present: Vec<u64>,
+    swapped: Vec<u64>,
+}
+
+impl ResidencyBitmap {
+    fn new(start: usize, page_size: usize, pages: usize) -> ResidencyBitmap {
+        let words = pages.div_ceil(64);
+        ResidencyBitmap {
+            start,
+            page_size,
+            pages,
+            present: vec![0; words],
+            swapped: vec![0; words],
+        }
+    }
+
+    fn set(bits: &mut [u64], page: usize) {
+        bits[page / 64] |= 1 << (page % 64);
+    }
+
+    fn get(bits: &[u64], page: usize) -> bool {
+        bits[page / 64] & (1 << (page % 64)) != 0
+    }
+
+    /// Returns the address of the first page in the bitmap
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    /// Returns the number of pages covered by the bitmap
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+    /// Returns whether the page at the given index is present in RAM
+    pub fn is_present(&self, page: usize) -> bool {
+        page < self.pages && ResidencyBitmap::get(&self.present, page)
+    }
+    /// Returns whether the page at the given index has been swapped out
+    pub fn is_swapped(&self, page: usize) -> bool {
+        page < self.pages && ResidencyBitmap::get(&self.swapped, page)
+    }
+    /// Returns the number of pages that are present in RAM
+    pub fn present_count(&self) -> usize {
+        self.present.iter().map(|w| w.count_ones() as usize).sum()
+    }
+    /// Returns the number of pages that have been swapped out
+    pub fn swapped_count(&self) -> usize {
+        self.swapped.iter().map(|w| w.count_ones() as usize).sum()
+    }
+    /// Returns the number of bytes that are present in RAM
+    pub fn resident_bytes(&self) -> usize {
+        self.present_count() * self.page_size
+    }
+    /// Returns the words of the present bitmap, where bit `i % 64` of word
+    /// `i / 64` is set if page `i` is present
+    pub fn present_bits(&self) -> &[u64] {
+        &self.present
+    }
+    /// Returns the words of the swapped bitmap, laid out like `present_bits`
+    pub fn swapped_bits(&self) -> &[u64] {
+        &self.swapped
+    }
+}
+
+/// Reader for `/proc/PID/pagemap`, which holds the file open so that several
+/// ranges can be looked up without reopening it.
+pub struct Pagemap {
+    file: File,
+    page_size: usize,
+}
+
+impl Pagemap {
+    /// Opens the pagemap of the passed in PID
+    pub fn open(pid: Pid) -> std::io::Result<Pagemap> {
+        ProcFs::default().pagemap(pid)
+    }
+
+    /// Returns the page size that entries are reported in
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Gets the entries for every page overlapping `start..end`
+    pub fn entries(&self, start: usize, end: usize) -> std::io::Result<Vec<PagemapEntry>> {
+        let mut vec = Vec::new();
+        self.for_each_entry(start, end, |entry| vec.push(entry))?;
+        Ok(vec)
+    }
+
+    /// Gets the entries for every page in the passed in MapRange
+    pub fn map_entries(&self, map: &MapRange) -> std::io::Result<Vec<PagemapEntry>> {
+        self.entries(map.start(), map.start() + map.size())
+    }
+
+    /// Summarizes which pages overlapping `start..end` are in RAM or swap
+    pub fn residency(&self, start: usize, end: usize) -> std::io::Result<ResidencyBitmap> {
+        let first = start / self.page_size;
+        let last = end.div_ceil(self.page_size);
+        let mut bitmap = ResidencyBitmap::new(
+            first * self.page_size,
+            self.page_size,
+            last.saturating_sub(first),
+        );
+        self.for_each_entry(start, end, |entry| {
+            let page = entry.address / bitmap.page_size - first;
+            if entry.is_present() {
+                ResidencyBitmap::set(&mut bitmap.present, page);
+            }
+            if entry.is_swapped() {
+                ResidencyBitmap::set(&mut bitmap.swapped, page);
+            }
+        })?;
+        Ok(bitmap)
+    }
+
+    /// Summarizes which pages in the passed in MapRange are in RAM or swap
+    pub fn map_residency(&self, map: &MapRange) -> std::io::Result<ResidencyBitmap> {
+        self.residency(map.start(), map.start() + map.size())
+    }
+
+    fn for_each_entry<F: FnMut(PagemapEntry)>(
+        &self,
+        start: usize,
+        end: usize,
+        mut f: F,
+    ) -> std::io::Result<()> {
+        let mut page = start / self.page_size;
+        let last = end.div_ceil(self.page_size);
+        let mut buffer = vec![0u8; CHUNK_ENTRIES * 8];
+        while page < last {
+            let count = std::cmp::min(last - page, CHUNK_ENTRIES);
+            let bytes = &mut buffer[..count * 8];
+            self.file.read_exact_at(bytes, page as u64 * 8)?;
+            for (i, raw) in bytes.chunks_exact(8).enumerate() {
+                let mut word = [0u8; 8];
+                word.copy_from_slice(raw);
+                f(PagemapEntry {
+                    address: (page + i) * self.page_size,
+                    raw: u64::from_ne_bytes(word),
+                });
+            }
+            page += count;
+        }
+        Ok(())
+    }
+}
+
+impl ProcFs {
+    /// Opens the pagemap of the passed in PID in this procfs
+    pub fn pagemap(&self, pid: Pid) -> std::io::Result<Pagemap> {
+        let file = File::open(self.path(pid, "pagemap"))?;
+        Ok(Pagemap {
+            file,
+            page_size: page_size(),
+        })
+    }
+}
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[test]
+fn test_pagemap_entry_bits() {
+    let present = PagemapEntry {
+        address: 0x1000,
+        raw: PRESENT | EXCLUSIVE | SOFT_DIRTY | 0x1234,
+    };
+    assert!(present.is_present());
+    assert!(!present.is_swapped());
+    assert!(present.is_exclusive());
+    assert!(present.is_soft_dirty());
+    assert!(!present.is_file_or_shared_anon());
+    assert_eq!(present.pfn(), Some(0x1234));
+    assert_eq!(present.swap_type(), None);
+
+    // Without CAP_SYS_ADMIN the PFN is zeroed
+    let hidden = PagemapEntry {
+        address: 0x1000,
+        raw: PRESENT | FILE_OR_SHARED_ANON,
+    };
+    assert_eq!(hidden.pfn(), None);
+    assert!(hidden.is_file_or_shared_anon());
+
+    let swapped = PagemapEntry {
+        address: 0x2000,
+        raw: SWAPPED | (0xabcd << 5) | 3,
+    };
+    assert!(!swapped.is_present());
+    assert_eq!(swapped.pfn(), None);
+    assert_eq!(swapped.swap_type(), Some(3));
+    assert_eq!(swapped.swap_offset(), Some(0xabcd));
+}
+
+#[test]
+fn test_pagemap_residency() {
+    let page_size = page_size();
+    let len = page_size * 4;
+    let addr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(addr, libc::MAP_FAILED);
+    let start = addr as usize;
+    unsafe {
+        *(start as *mut u8) = 1;
+        *((start + 2 * page_size) as *mut u8) = 1;
+    }
+
+    let pagemap = Pagemap::open(std::process::id() as Pid).unwrap();
+    let entries = pagemap.entries(start, start + len).unwrap();
+    let bitmap = pagemap.residency(start, start + len).unwrap();
+    unsafe { libc::munmap(addr, len) };
+
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[1].address(), start + page_size);
+    let present: Vec<bool> = entries.iter().map(|e| e.is_present()).collect();
+    assert_eq!(present, vec![true, false, true, false]);
+
+    assert_eq!(bitmap.start(), start);
+    assert_eq!(bitmap.pages(), 4);
+    assert_eq!(bitmap.present_count(), 2);
+    assert_eq!(bitmap.present_bits(), &[0b101]);
+    assert_eq!(bitmap.resident_bytes(), 2 * page_size);
+    assert!(!bitmap.is_present(4));
+}
@@ -0,0 +1,166 @@
+use libc;
+use std;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::Pid;
+
+/// A procfs mount that the Linux functions in this crate read from.
+///
+/// The free functions such as [`get_process_maps`](fn.get_process_maps.html)
+/// read from `/proc`, which is what `ProcFs::default()` points at. Creating a
+/// `ProcFs` with another root makes it possible to inspect the host's
+/// processes from inside a container that has the host procfs mounted
+/// elsewhere:
+///
+/// ```rust,no_run
+/// use proc_maps::linux_maps::ProcFs;
+///
+/// let procfs = ProcFs::new("/host/proc");
+/// let maps = procfs.get_process_maps(1).unwrap();
+/// ```
+///
+/// PIDs passed to its methods are resolved in the PID namespace of that
+/// procfs mount.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcFs {
+    root: PathBuf,
+}
+
+impl ProcFs {
+    /// Creates a ProcFs that reads from the procfs mounted at `root`
+    pub fn new<P: Into<PathBuf>>(root: P) -> ProcFs {
+        ProcFs { root: root.into() }
+    }
+
+    /// Returns the path this procfs is mounted at
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path of a file in the passed in PID's procfs directory
+    pub(super) fn path(&self, pid: Pid, name: &str) -> PathBuf {
+        self.root.join(pid.to_string()).join(name)
+    }
+
+    /// Reads a file from the passed in PID's procfs directory into `buffer`,
+    /// replacing its contents
+    pub(super) fn read_bytes(
+        &self,
+        pid: Pid,
+        name: &str,
+        buffer: &mut Vec<u8>,
+    ) -> std::io::Result<()> {
+        let mut file = File::open(self.path(pid, name))?;
+
+        // Check that the file is not too big
+        let metadata = file.metadata()?;
+        if metadata.len() > 0x10000000 {
+            return Err(std::io::Error::from_raw_os_error(libc::EFBIG));
+        }
+
+        buffer.clear();
+        file.read_to_end(buffer)?;
+        Ok(())
+    }
+
+    /// Reads a text file from the passed in PID's procfs directory into a
+    /// String
+    pub(super) fn read_string(&self, pid: Pid, name: &str) -> std::io::Result<String> {
+        let mut contents = Vec::new();
+        self.read_bytes(pid, name, &mut contents)?;
+        String::from_utf8(contents)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Default for ProcFs {
+    /// Returns a ProcFs for the procfs mounted at `/proc`
+    fn default() -> ProcFs {
+        ProcFs::new("/proc")
+    }
+}
+
+#[test]
+fn test_fake_procfs() {
+    use super::{MapsReader, RegionKind};
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    let root = std::env::temp_dir().join(format!("proc-maps-procfs-{}", std::process::id()));
+    let _ = remove_dir_all(&root);
+    let procfs = ProcFs::new(&root);
+    assert_eq!(procfs.root(), root.as_path());
+    assert_eq!(ProcFs::default().root(), Path::new("/proc"));
+
+    // A process with three threads, where 4242 has a `[stack:TID]` annotation,
+    // and 4243 only has the stack pointer in its syscall file
+    let maps = include_str!("../../ci/testdata/map_kinds.txt");
+    let dir = root.join("4241");
+    create_dir_all(dir.join("task/4241")).unwrap();
+    create_dir_all(dir.join("task/4242")).unwrap();
+    create_dir_all(dir.join("task/4243")).unwrap();
+    write(dir.join("maps"), maps).unwrap();
+    write(dir.join("task/4243/maps"), maps).unwrap();
+    write(
+        dir.join("smaps"),
+        &include_bytes!("../../ci/testdata/smaps.txt")[..],
+    )
+    .unwrap();
+    write(
+        dir.join("mountinfo"),
+        include_str!("../../ci/testdata/mountinfo.txt"),
+    )
+    .unwrap();
+    let stat = format!(
+        "4241 (fake process) S 1 4241 4241 0 -1 4194560 100 0 0 0 1 2 0 0 20 0 3 0 \
+         500 10000000 200 18446744073709551615 1 1 {} 0 0 0 0 0 0 0 0 0 17 3 0 0 0 0 0",
+        0x7ffc8a3e1000usize
+    );
+    write(dir.join("stat"), stat).unwrap();
+    write(
+        dir.join("task/4243/syscall"),
+        "202 0x1 0x2 0x3 0x4 0x5 0x6 0x7f0b55000800 0x7f0b4f000400\n",
+    )
+    .unwrap();
+
+    let ranges = procfs.get_process_maps(4241).unwrap();
+    assert_eq!(ranges, super::parse_maps(maps).unwrap());
+    let (lenient, skipped) = procfs.get_process_maps_lenient(4241).unwrap();
+    assert_eq!(lenient, ranges);
+    assert!(skipped.is_empty());
+    let mut reader = MapsReader::with_procfs(procfs.clone());
+    assert_eq!(reader.read(4241).unwrap().count(), ranges.len());
+    assert_eq!(
+        procfs.map_file_path(4241, &ranges[0]),
+        dir.join("map_files/55d0c1e00000-55d0c1e28000")
+    );
+
+    // The query ioctl isn't supported on regular files, so this goes through
+    // the text fallback
+    let heap = procfs.query_address(4241, 0x55d0c2f1a010).unwrap().unwrap();
+    assert_eq!(heap.kind(), RegionKind::Heap);
+
+    // Without smaps_rollup the totals are summed from smaps
+    let smaps = procfs.get_process_smaps(4241).unwrap();
+    assert_eq!(smaps.len(), 3);
+    let totals = procfs.get_process_smaps_rollup(4241).unwrap();
+    assert_eq!(totals.usage.rss, 1568 * 1024);
+
+    let mounts = procfs
+        .device_mounts(4241, super::DeviceNumber::new(253, 1))
+        .unwrap();
+    assert_eq!(mounts.len(), 2);
+    assert_eq!(mounts[1].mount_point, PathBuf::from("/mnt/projects"));
+
+    assert_eq!(procfs.get_thread_maps(4241, 4243).unwrap(), ranges);
+    let stacks = procfs.get_thread_stacks(4241).unwrap();
+    assert_eq!(stacks.len(), 3);
+    assert_eq!(stacks[&4241].kind(), RegionKind::Stack);
+    assert_eq!(stacks[&4242].kind(), RegionKind::ThreadStack(4242));
+    assert_eq!(stacks[&4243].start(), 0x7f0b55000000);
+
+    let missing = procfs.get_process_maps(4244).unwrap_err();
+    assert_eq!(missing.kind(), std::io::ErrorKind::NotFound);
+    remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,235 @@
+use libc;
+use std;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+
+use super::{DeviceNumber, MapRange, MapsIter, Pid, ProcFs, DELETED_SUFFIX};
+use Permissions;
+
+/// `_IOWR('f', 17, struct procmap_query)`, which has the same value on every
+/// architecture
+const PROCMAP_QUERY: u32 = 0xc068_6611;
+
+const PROCMAP_QUERY_VMA_READABLE: u64 = 0x01;
+const PROCMAP_QUERY_VMA_WRITABLE: u64 = 0x02;
+const PROCMAP_QUERY_VMA_EXECUTABLE: u64 = 0x04;
+const PROCMAP_QUERY_VMA_SHARED: u64 = 0x08;
+
+/// The largest name the kernel will return is a path of `PATH_MAX` bytes,
+/// plus the ` (deleted)` marker and the nul terminator
+const NAME_BUFFER_SIZE: usize = libc::PATH_MAX as usize + 16;
+
+/// `struct procmap_query` from `linux/fs.h`
+#[repr(C)]
+#[derive(Default)]
+struct ProcmapQuery {
+    size: u64,
+    query_flags: u64,
+    query_addr: u64,
+    vma_start: u64,
+    vma_end: u64,
+    vma_flags: u64,
+    vma_page_size: u64,
+    vma_offset: u64,
+    inode: u64,
+    dev_major: u32,
+    dev_minor: u32,
+    vma_name_size: u32,
+    build_id_size: u32,
+    vma_name_addr: u64,
+    build_id_addr: u64,
+}
+
+/// Looks up the mapping that covers an address in another process, without
+/// parsing all of `/proc/PID/maps`.
+///
+/// On Linux 6.11 and later this uses the `PROCMAP_QUERY` ioctl. Older kernels
+/// don't support it, so the text of the maps file is parsed instead. The file
+/// is kept open between queries, so a MapsQuery should be reused when
+/// resolving many addresses.
+#[derive(Debug)]
+pub struct MapsQuery {
+    file: File,
+    ioctl: bool,
+    buffer: Vec<u8>,
+}
+
+impl MapsQuery {
+    /// Opens `/proc/PID/maps` for querying
+    pub fn open(pid: Pid) -> std::io::Result<MapsQuery> {
+        ProcFs::default().maps_query(pid)
+    }
+
+    fn from_file(file: File) -> MapsQuery {
+        MapsQuery {
+            file,
+            ioctl: true,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the mapping that contains `addr`, or `None` if the address
+    /// isn't mapped
+    pub fn query(&mut self, addr: usize) -> std::io::Result<Option<MapRange>> {
+        if self.ioctl {
+            match self.query_ioctl(addr) {
+                // ENOTTY means the kernel doesn't know about the ioctl
+                Err(ref e) if e.raw_os_error() == Some(libc::ENOTTY) => self.ioctl = false,
+                result => return result,
+            }
+        }
+        self.query_text(addr)
+    }
+
+    fn query_ioctl(&mut self, addr: usize) -> std::io::Result<Option<MapRange>> {
+        self.buffer.resize(NAME_BUFFER_SIZE, 0);
+        let mut query = ProcmapQuery {
+            size: std::mem::size_of::<ProcmapQuery>() as u64,
+            query_addr: addr as u64,
+            vma_name_size: self.buffer.len() as u32,
+            vma_name_addr: self.buffer.as_mut_ptr() as u64,
+            ..ProcmapQuery::default()
+        };
+        let ret = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                PROCMAP_QUERY as _,
+                &mut query as *mut ProcmapQuery,
+            )
+        };
+        if ret != 0 {
+            let err = std::io::Error::last_os_error();
+            return match err.raw_os_error() {
+                // No mapping covers the address
+                Some(libc::ENOENT) => Ok(None),
+                _ => Err(err),
+            };
+        }
+
+        // The returned size includes the nul terminator, and is zero for
+        // mappings without a name
+        let mut name = &self.buffer[..(query.vma_name_size as usize).saturating_sub(1)];
+        let deleted = name.ends_with(DELETED_SUFFIX.as_bytes());
+        if deleted {
+            name = &name[..name.len() - DELETED_SUFFIX.len()];
+        }
+        let pathname = Some(name)
+            .filter(|n| !n.is_empty())
+            .map(|n| PathBuf::from(OsString::from_vec(n.to_vec())));
+
+        Ok(Some(MapRange {
+            range_start: query.vma_start as usize,
+            range_end: query.vma_end as usize,
+            offset: query.vma_offset as usize,
+            dev: DeviceNumber::new(query.dev_major, query.dev_minor),
+            perms: Permissions::new(
+                query.vma_flags & PROCMAP_QUERY_VMA_READABLE != 0,
+                query.vma_flags & PROCMAP_QUERY_VMA_WRITABLE != 0,
+                query.vma_flags & PROCMAP_QUERY_VMA_EXECUTABLE != 0,
+                query.vma_flags & PROCMAP_QUERY_VMA_SHARED != 0,
+            ),
+            inode: query.inode as usize,
+            pathname,
+            deleted,
+            memory_usage: None,
+            vm_flags: None,
+            build_id: None,
+        }))
+    }
+
+    fn query_text(&mut self, addr: usize) -> std::io::Result<Option<MapRange>> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.buffer.clear();
+        self.file.read_to_end(&mut self.buffer)?;
+        for range in MapsIter::new(&self.buffer) {
+            let range = range?;
+            if range.start() <= addr && addr - range.start() < range.size() {
+                return Ok(Some(range.into()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Returns the mapping that contains `addr` in the passed in PID, or `None` if
+/// the address isn't mapped.
+///
+/// This is a shortcut for [`MapsQuery`](struct.MapsQuery.html), which should
+/// be used instead when looking up more than one address.
+pub fn query_address(pid: Pid, addr: usize) -> std::io::Result<Option<MapRange>> {
+    ProcFs::default().query_address(pid, addr)
+}
+
+impl ProcFs {
+    /// Opens the maps of the passed in PID in this procfs for querying, like
+    /// [`MapsQuery::open`](struct.MapsQuery.html#method.open)
+    pub fn maps_query(&self, pid: Pid) -> std::io::Result<MapsQuery> {
+        Ok(MapsQuery::from_file(File::open(self.path(pid, "maps"))?))
+    }
+
+    /// Returns the mapping that contains `addr` in the passed in PID from this
+    /// procfs, like [`query_address`](fn.query_address.html)
+    pub fn query_address(&self, pid: Pid, addr: usize) -> std::io::Result<Option<MapRange>> {
+        self.maps_query(pid)?.query(addr)
+    }
+}
+
+#[test]
+fn test_query_address() {
+    let pid = std::process::id() as Pid;
+    let mut query = MapsQuery::open(pid).unwrap();
+
+    // Code in this test binary, and a deleted file, which the ioctl reports
+    // with the ` (deleted)` marker like the text does
+    let path = std::env::temp_dir().join(format!("proc-maps-query-{}", pid));
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    file.set_len(8192).unwrap();
+    let mapped = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            8192,
+            libc::PROT_READ,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    assert_ne!(mapped, libc::MAP_FAILED);
+    std::fs::remove_file(&path).unwrap();
+
+    let code = test_query_address as *const () as usize;
+    let addrs = [code, mapped as usize + 4100];
+    for &addr in addrs.iter() {
+        let text = query.query_text(addr).unwrap().unwrap();
+        assert!(text.start() <= addr && addr < text.start() + text.size());
+        match query.query_ioctl(addr) {
+            Ok(ioctl) => assert_eq!(ioctl, Some(text)),
+            // Kernels before 6.11
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOTTY) => {}
+            Err(e) => panic!("{}", e),
+        }
+    }
+    let map = query.query(mapped as usize).unwrap().unwrap();
+    unsafe { libc::munmap(mapped, 8192) };
+    assert_eq!(map.filename(), Some(path.as_path()));
+    assert!(map.is_deleted());
+    assert!(map.perms.is_shared());
+
+    assert_eq!(query.query(0).unwrap(), None);
+    assert_eq!(query.query_text(0).unwrap(), None);
+    let exe = query_address(pid, code).unwrap();
+    assert_eq!(
+        exe.unwrap().filename(),
+        std::env::current_exe().ok().as_deref()
+    );
+}
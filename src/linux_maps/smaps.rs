@@ -0,0 +1,384 @@
+use std;
+
+use super::{MapRange, MapRangeRef, ParseError, ParseField, VmFlags};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::{Pid, ProcFs};
+
+/// Memory accounting for a single region, as reported by `/proc/PID/smaps`.
+///
+/// All values are in bytes. Fields that the running kernel doesn't report are
+/// left as zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MemoryUsage {
+    pub size: usize,
+    pub kernel_page_size: usize,
+    pub mmu_page_size: usize,
+    pub rss: usize,
+    pub pss: usize,
+    pub pss_dirty: usize,
+    pub shared_clean: usize,
+    pub shared_dirty: usize,
+    pub private_clean: usize,
+    pub private_dirty: usize,
+    pub referenced: usize,
+    pub anonymous: usize,
+    pub ksm: usize,
+    pub lazy_free: usize,
+    pub anon_huge_pages: usize,
+    pub shmem_pmd_mapped: usize,
+    pub file_pmd_mapped: usize,
+    pub shared_hugetlb: usize,
+    pub private_hugetlb: usize,
+    pub swap: usize,
+    pub swap_pss: usize,
+    pub locked: usize,
+}
+
+impl MemoryUsage {
+    /// Stores the value of a `Key: N kB` line, ignoring keys that we don't
+    /// know about
+    fn set_field(&mut self, key: &str, kb: usize) {
+        let field = match key {
+            "Size" => &mut self.size,
+            "KernelPageSize" => &mut self.kernel_page_size,
+            "MMUPageSize" => &mut self.mmu_page_size,
+            "Rss" => &mut self.rss,
+            "Pss" => &mut self.pss,
+            "Pss_Dirty" => &mut self.pss_dirty,
+            "Shared_Clean" => &mut self.shared_clean,
+            "Shared_Dirty" => &mut self.shared_dirty,
+            "Private_Clean" => &mut self.private_clean,
+            "Private_Dirty" => &mut self.private_dirty,
+            "Referenced" => &mut self.referenced,
+            "Anonymous" => &mut self.anonymous,
+            "KSM" => &mut self.ksm,
+            "LazyFree" => &mut self.lazy_free,
+            "AnonHugePages" => &mut self.anon_huge_pages,
+            "ShmemPmdMapped" => &mut self.shmem_pmd_mapped,
+            "FilePmdMapped" => &mut self.file_pmd_mapped,
+            "Shared_Hugetlb" => &mut self.shared_hugetlb,
+            "Private_Hugetlb" => &mut self.private_hugetlb,
+            "Swap" => &mut self.swap,
+            "SwapPss" => &mut self.swap_pss,
+            "Locked" => &mut self.locked,
+            _ => return,
+        };
+        *field = kb * 1024;
+    }
+
+    /// Adds the sizes from another region onto this one. The page sizes are
+    /// per-region properties, and aren't summed.
+    fn accumulate(&mut self, other: &MemoryUsage) {
+        self.size += other.size;
+        self.rss += other.rss;
+        self.pss += other.pss;
+        self.pss_dirty += other.pss_dirty;
+        self.shared_clean += other.shared_clean;
+        self.shared_dirty += other.shared_dirty;
+        self.private_clean += other.private_clean;
+        self.private_dirty += other.private_dirty;
+        self.referenced += other.referenced;
+        self.anonymous += other.anonymous;
+        self.ksm += other.ksm;
+        self.lazy_free += other.lazy_free;
+        self.anon_huge_pages += other.anon_huge_pages;
+        self.shmem_pmd_mapped += other.shmem_pmd_mapped;
+        self.file_pmd_mapped += other.file_pmd_mapped;
+        self.shared_hugetlb += other.shared_hugetlb;
+        self.private_hugetlb += other.private_hugetlb;
+        self.swap += other.swap;
+        self.swap_pss += other.swap_pss;
+        self.locked += other.locked;
+    }
+}
+
+/// Memory accounting for a whole process, as reported by
+/// `/proc/PID/smaps_rollup`.
+///
+/// The `Pss_Anon`, `Pss_File` and `Pss_Shmem` breakdown is only reported by the
+/// rollup file itself, so those fields are `None` when the totals had to be
+/// summed from `/proc/PID/smaps` instead. The `size`, `kernel_page_size` and
+/// `mmu_page_size` fields of `usage` are not reported by the rollup file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MemoryTotals {
+    pub usage: MemoryUsage,
+    pub pss_anon: Option<usize>,
+    pub pss_file: Option<usize>,
+    pub pss_shmem: Option<usize>,
+}
+
+impl MemoryTotals {
+    /// Sums the memory usage of every region that has it attached
+    pub fn from_ranges(ranges: &[MapRange]) -> MemoryTotals {
+        let mut totals = MemoryTotals::default();
+        for usage in ranges.iter().filter_map(|r| r.memory_usage()) {
+            totals.usage.accumulate(usage);
+        }
+        totals
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Gets a Vec of [`MapRange`](struct.MapRange.html) structs for the passed in
+/// PID, with the per-region [`MemoryUsage`](struct.MemoryUsage.html) from
+/// `/proc/PID/smaps` attached.
+pub fn get_process_smaps(pid: Pid) -> std::io::Result<Vec<MapRange>> {
+    ProcFs::default().get_process_smaps(pid)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Gets the memory totals for the passed in PID from `/proc/PID/smaps_rollup`.
+///
+/// On kernels without the rollup file (before 4.14) this falls back to
+/// summing the regions in `/proc/PID/smaps`, which is much slower.
+pub fn get_process_smaps_rollup(pid: Pid) -> std::io::Result<MemoryTotals> {
+    ProcFs::default().get_process_smaps_rollup(pid)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl ProcFs {
+    /// Gets the maps of the passed in PID from this procfs with their memory
+    /// usage attached, like [`get_process_smaps`](fn.get_process_smaps.html)
+    pub fn get_process_smaps(&self, pid: Pid) -> std::io::Result<Vec<MapRange>> {
+        let mut contents = Vec::new();
+        self.read_bytes(pid, "smaps", &mut contents)?;
+        parse_smaps(&contents)
+    }
+
+    /// Gets the memory totals for the passed in PID from this procfs, like
+    /// [`get_process_smaps_rollup`](fn.get_process_smaps_rollup.html)
+    pub fn get_process_smaps_rollup(&self, pid: Pid) -> std::io::Result<MemoryTotals> {
+        match self.read_string(pid, "smaps_rollup") {
+            Ok(contents) => parse_smaps_rollup(&contents),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(MemoryTotals::from_ranges(&self.get_process_smaps(pid)?))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Splits a `Key: value` line from smaps into its key and value
+fn split_field(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(':')?;
+    let key = &line[..colon];
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((key, line[colon + 1..].trim()))
+}
+
+/// Parses a `N kB` value from smaps, where `line` is the whole line for the
+/// error
+fn parse_kb(line: &str, value: &str) -> Result<Option<usize>, ParseError> {
+    let mut split = value.split_whitespace();
+    let number = split.next();
+    match (number, split.next()) {
+        (Some(n), Some("kB")) => match n.parse::<usize>() {
+            Ok(i) => Ok(Some(i)),
+            Err(_) => Err(ParseError::new(ParseField::Field, line.as_bytes())),
+        },
+        // Fields like THPeligible and ProtectionKey aren't sizes
+        _ => Ok(None),
+    }
+}
+
+pub(super) fn parse_smaps(contents: &[u8]) -> std::io::Result<Vec<MapRange>> {
+    let mut vec: Vec<MapRange> = Vec::new();
+    for (i, line) in contents.split(|&b| b == b'\n').enumerate() {
+        if line.iter().all(|b| b.is_ascii_whitespace()) {
+            break;
+        }
+
+        // Only the header lines can have a pathname that isn't valid UTF-8
+        let text = std::str::from_utf8(line).ok();
+        let (key, value) = match text.and_then(split_field) {
+            Some(field) => field,
+            None => {
+                let mut range: MapRange = MapRangeRef::parse(line)
+                    .map_err(|e| e.at_line(i + 1))?
+                    .into();
+                range.memory_usage = Some(MemoryUsage::default());
+                vec.push(range);
+                continue;
+            }
+        };
+        let line = text.unwrap();
+
+        // Fields belong to the region whose header came before them
+        let range = match vec.last_mut() {
+            Some(range) => range,
+            None => {
+                return Err(ParseError::new(ParseField::Field, line.as_bytes())
+                    .at_line(i + 1)
+                    .into())
+            }
+        };
+        if key == "VmFlags" {
+            range.vm_flags = Some(VmFlags::parse(value));
+            continue;
+        }
+        let usage = range.memory_usage.get_or_insert_with(MemoryUsage::default);
+        if let Some(kb) = parse_kb(line, value).map_err(|e| e.at_line(i + 1))? {
+            usage.set_field(key, kb);
+        }
+    }
+    Ok(vec)
+}
+
+fn parse_smaps_rollup(contents: &str) -> std::io::Result<MemoryTotals> {
+    let mut totals = MemoryTotals::default();
+    let mut lines = contents.split('\n');
+
+    // The header is a maps line covering the whole address space
+    MapRangeRef::parse(lines.next().unwrap_or("").as_bytes())?;
+
+    for (i, line) in lines.enumerate() {
+        if line.split_whitespace().next().is_none() {
+            break;
+        }
+        // The header is line 1
+        let at_line = |e: ParseError| e.at_line(i + 2);
+        let (key, value) = match split_field(line) {
+            Some(field) => field,
+            None => return Err(at_line(ParseError::new(ParseField::Field, line.as_bytes())).into()),
+        };
+        let kb = match parse_kb(line, value).map_err(at_line)? {
+            Some(kb) => kb,
+            None => continue,
+        };
+        match key {
+            "Pss_Anon" => totals.pss_anon = Some(kb * 1024),
+            "Pss_File" => totals.pss_file = Some(kb * 1024),
+            "Pss_Shmem" => totals.pss_shmem = Some(kb * 1024),
+            _ => totals.usage.set_field(key, kb),
+        }
+    }
+    Ok(totals)
+}
+
+#[test]
+fn test_parse_smaps() {
+    let contents = include_bytes!("../../ci/testdata/smaps.txt");
+    let vec = parse_smaps(contents).unwrap();
+    assert_eq!(vec.len(), 3);
+
+    let fish = &vec[0];
+    assert_eq!(fish.start(), 0x00400000);
+    assert_eq!(fish.filename(), Some(std::path::Path::new("/usr/bin/fish")));
+    let usage = fish.memory_usage().unwrap();
+    assert_eq!(usage.size, 1052 * 1024);
+    assert_eq!(usage.rss, 920 * 1024);
+    assert_eq!(usage.pss, 460 * 1024);
+    assert_eq!(usage.shared_clean, 920 * 1024);
+    assert_eq!(usage.private_dirty, 0);
+    assert_eq!(fish.vm_flags().unwrap().to_string(), "rd ex mr mw me dw sd");
+
+    let anon = &vec[1];
+    assert_eq!(anon.filename(), None);
+    assert_eq!(anon.memory_usage().unwrap().locked, 8 * 1024);
+    assert!(anon.vm_flags().unwrap().contains(VmFlags::LOCKED));
+    assert!(vec[2].vm_flags().unwrap().contains(VmFlags::HUGEPAGE));
+
+    let heap = vec[2].memory_usage().unwrap();
+    assert_eq!(
+        heap,
+        &MemoryUsage {
+            size: 772 * 1024,
+            kernel_page_size: 4 * 1024,
+            mmu_page_size: 4 * 1024,
+            rss: 640 * 1024,
+            pss: 640 * 1024,
+            pss_dirty: 512 * 1024,
+            private_clean: 128 * 1024,
+            private_dirty: 512 * 1024,
+            referenced: 600 * 1024,
+            anonymous: 640 * 1024,
+            swap: 132 * 1024,
+            swap_pss: 66 * 1024,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_parse_smaps_errors() {
+    let parse_error = |err: std::io::Error| {
+        let err = err.get_ref().unwrap().downcast_ref::<ParseError>().unwrap();
+        (err.line(), err.field(), err.text().to_string())
+    };
+
+    // A field needs a header before it
+    let err = parse_smaps(b"Rss:                   4 kB\n").unwrap_err();
+    assert_eq!(
+        parse_error(err),
+        (
+            1,
+            ParseField::Field,
+            "Rss:                   4 kB".to_string()
+        )
+    );
+
+    let contents = b"00400000-00507000 r-xp 00000000 00:14 0\nSize: 4 kB\nRss: x kB\n";
+    let err = parse_smaps(contents).unwrap_err();
+    assert_eq!(
+        parse_error(err),
+        (3, ParseField::Field, "Rss: x kB".to_string())
+    );
+    let err = parse_smaps(b"00400000-00507000 r-xp 00000000 0014 0\n").unwrap_err();
+    assert_eq!(parse_error(err), (1, ParseField::Dev, "0014".to_string()));
+
+    let contents = "00400000-00507000 ---p 00000000 00:00 0 [rollup]\nRss: 4 kB\nPss 4 kB\n";
+    let err = parse_smaps_rollup(contents).unwrap_err();
+    assert_eq!(
+        parse_error(err),
+        (3, ParseField::Field, "Pss 4 kB".to_string())
+    );
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_get_process_smaps() {
+    let vec = get_process_smaps(std::process::id() as Pid).unwrap();
+    assert!(vec.iter().all(|r| r.memory_usage().is_some()));
+    assert!(vec.iter().all(|r| r.vm_flags().is_some()));
+    assert!(vec.iter().any(|r| r.memory_usage().unwrap().rss > 0));
+}
+
+#[test]
+fn test_parse_smaps_rollup() {
+    let contents = include_str!("../../ci/testdata/smaps_rollup.txt");
+    let totals = parse_smaps_rollup(contents).unwrap();
+    assert_eq!(totals.usage.rss, 1568 * 1024);
+    assert_eq!(totals.usage.pss, 1108 * 1024);
+    assert_eq!(totals.pss_anon, Some(648 * 1024));
+    assert_eq!(totals.pss_file, Some(460 * 1024));
+    assert_eq!(totals.pss_shmem, Some(0));
+    assert_eq!(totals.usage.swap, 132 * 1024);
+    assert_eq!(totals.usage.locked, 8 * 1024);
+
+    // Summing the matching smaps file gives the same totals, minus the
+    // breakdown that only the rollup file has
+    let smaps = parse_smaps(include_bytes!("../../ci/testdata/smaps.txt")).unwrap();
+    let summed = MemoryTotals::from_ranges(&smaps);
+    assert_eq!(summed.pss_anon, None);
+    assert_eq!(
+        summed.usage,
+        MemoryUsage {
+            size: (1052 + 8 + 772) * 1024,
+            kernel_page_size: 0,
+            mmu_page_size: 0,
+            ..totals.usage
+        }
+    );
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_get_process_smaps_rollup() {
+    let totals = get_process_smaps_rollup(std::process::id() as Pid).unwrap();
+    assert!(totals.usage.rss > 0);
+    assert!(totals.usage.pss > 0);
+}
@@ -0,0 +1,372 @@
+use std;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::collections::HashMap;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::io::BufReader;
+use std::io::{Read, Seek};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::{DeviceNumber, MapRange, Module, Pid, ProcFs};
+use elf::{self, ElfReader, SHT_DYNSYM, SHT_SYMTAB};
+
+/// A function or data object from the symbol table of an ELF file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The name of the symbol, which is still mangled for C++ and Rust
+    pub name: String,
+    /// The ELF virtual address of the symbol, before the load bias is added
+    pub value: usize,
+    /// The size of the symbol in bytes, which is zero if it isn't known
+    pub size: usize,
+}
+
+impl Symbol {
+    /// Returns whether an ELF virtual address falls within the symbol. A
+    /// symbol without a size only contains its own address.
+    pub fn contains(&self, vaddr: usize) -> bool {
+        self.value <= vaddr && vaddr - self.value < self.size.max(1)
+    }
+}
+
+/// The symbols of an ELF file from its `.symtab` and `.dynsym` sections,
+/// sorted by address.
+///
+/// Stripped files only have `.dynsym`, which holds the exported symbols that
+/// the dynamic loader needs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    /// The furthest end of any symbol up to and including each index, so that
+    /// lookups know when no earlier symbol can contain an address
+    max_ends: Vec<usize>,
+}
+
+impl SymbolTable {
+    /// Reads the symbol tables of an ELF file
+    pub fn read<R: Read + Seek>(reader: R) -> std::io::Result<SymbolTable> {
+        let mut elf = ElfReader::new(reader)?;
+        let is_64 = elf.header().is_64;
+        let sections = elf.section_headers()?;
+        let mut symbols = Vec::new();
+        for section in sections
+            .iter()
+            .filter(|s| s.sh_type == SHT_SYMTAB || s.sh_type == SHT_DYNSYM)
+        {
+            let strings = match sections.get(section.sh_link as usize) {
+                Some(strings) => elf.read_section(strings)?,
+                None => return Err(elf::invalid_data("invalid symbol string table")),
+            };
+            let entries = elf.read_section(section)?;
+            for entry in elf::symbols(&entries, is_64) {
+                if !entry.is_defined_code_or_data() {
+                    continue;
+                }
+                let name = match elf::string_at(&strings, entry.st_name) {
+                    Some(name) if !name.is_empty() => name,
+                    _ => continue,
+                };
+                symbols.push(Symbol {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    value: entry.st_value as usize,
+                    size: entry.st_size as usize,
+                });
+            }
+        }
+        Ok(SymbolTable::from_symbols(symbols))
+    }
+
+    fn from_symbols(mut symbols: Vec<Symbol>) -> SymbolTable {
+        // Exported symbols are in both tables
+        symbols.sort_by(|a, b| (a.value, &a.name).cmp(&(b.value, &b.name)));
+        symbols.dedup();
+        let max_ends = symbols
+            .iter()
+            .scan(0, |max_end: &mut usize, s| {
+                *max_end = (*max_end).max(s.value.saturating_add(s.size.max(1)));
+                Some(*max_end)
+            })
+            .collect();
+        SymbolTable { symbols, max_ends }
+    }
+
+    /// Returns the symbols, sorted by address
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Finds the symbol that contains an ELF virtual address, along with the
+    /// offset of the address from the start of the symbol
+    pub fn find(&self, vaddr: usize) -> Option<(&Symbol, usize)> {
+        let end = self.symbols.partition_point(|s| s.value <= vaddr);
+        // The closest symbol can be an alias or a label without a size inside
+        // the one that actually contains the address, so keep looking back
+        // until no earlier symbol reaches the address
+        (0..end)
+            .rev()
+            .take_while(|&i| self.max_ends[i] > vaddr)
+            .map(|i| &self.symbols[i])
+            .find(|s| s.contains(vaddr))
+            .map(|s| (s, vaddr - s.value))
+    }
+
+    /// Finds a symbol by its name
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+/// Resolves addresses in a process to the symbols of its loaded ELF files.
+///
+/// The modules are read once when the resolver is created, and again by
+/// [`refresh`](#method.refresh). The symbol table of each file is only read
+/// the first time an address in it is resolved, and is kept by the file's
+/// device and inode, so refreshing doesn't read it again, and files that are
+/// loaded more than once share a table.
+///
+/// ```rust,no_run
+/// use proc_maps::linux_maps::SymbolResolver;
+///
+/// # let pid = 1;
+/// # let addr = 0;
+/// let mut resolver = SymbolResolver::new(pid).unwrap();
+/// if let Some((module, symbol, offset)) = resolver.resolve(addr) {
+///     println!("{}+{:#x} in {:?}", symbol.name, offset, module.filename());
+/// }
+/// ```
+pub struct SymbolResolver {
+    procfs: ProcFs,
+    pid: Pid,
+    modules: Vec<Module>,
+    tables: HashMap<(DeviceNumber, usize), SymbolTable>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl SymbolResolver {
+    /// Creates a resolver for the modules of the passed in PID
+    pub fn new(pid: Pid) -> std::io::Result<SymbolResolver> {
+        SymbolResolver::with_procfs(ProcFs::default(), pid)
+    }
+
+    /// Creates a resolver that reads the modules of the passed in PID from
+    /// the passed in procfs
+    pub fn with_procfs(procfs: ProcFs, pid: Pid) -> std::io::Result<SymbolResolver> {
+        let modules = procfs.get_process_modules(pid)?;
+        Ok(SymbolResolver {
+            procfs,
+            pid,
+            modules,
+            tables: HashMap::new(),
+        })
+    }
+
+    /// Reads the modules again, for after the process has loaded or unloaded
+    /// shared libraries
+    pub fn refresh(&mut self) -> std::io::Result<()> {
+        self.modules = self.procfs.get_process_modules(self.pid)?;
+        Ok(())
+    }
+
+    /// Returns the modules that addresses are resolved against
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    /// Resolves an address in the process to the module it is in, the
+    /// symbol that contains it and its offset from the start of the symbol.
+    ///
+    /// Returns `None` if the address isn't in a mapping of an ELF file, or
+    /// the file has no symbol that covers it.
+    pub fn resolve(&mut self, addr: usize) -> Option<(&Module, &Symbol, usize)> {
+        // A module's range can have gaps between its segments, so look for the
+        // segment that maps the address
+        let index = self.modules.iter().position(|m| {
+            m.segments()
+                .iter()
+                .any(|s| s.to_file_offset(addr).is_some())
+        })?;
+        let key = self.load_table(index);
+        let module = &self.modules[index];
+        let segment = module
+            .segments()
+            .iter()
+            .find(|s| s.to_file_offset(addr).is_some())?;
+        let vaddr = segment.to_elf_vaddr(addr, module.program_headers())?;
+        let (symbol, offset) = self.tables[&key].find(vaddr)?;
+        Some((module, symbol, offset))
+    }
+
+    /// Finds the address that a symbol is loaded at, searching the modules
+    /// in address order
+    pub fn address_of(&mut self, name: &str) -> Option<usize> {
+        for index in 0..self.modules.len() {
+            let key = self.load_table(index);
+            let symbol = match self.tables[&key].lookup(name) {
+                Some(symbol) => symbol,
+                None => continue,
+            };
+            let module = &self.modules[index];
+            let addr = module
+                .segments()
+                .iter()
+                .filter_map(|s| s.elf_vaddr_to_addr(symbol.value, module.program_headers()))
+                .next();
+            if addr.is_some() {
+                return addr;
+            }
+        }
+        None
+    }
+
+    /// Reads the symbol table of a module if it hasn't been read already,
+    /// returning the key it is cached under. Files that can't be read get an
+    /// empty table, so that they aren't tried again.
+    fn load_table(&mut self, index: usize) -> (DeviceNumber, usize) {
+        let module = &self.modules[index];
+        let first = &module.segments()[0];
+        let key = (first.dev, first.inode);
+        if !self.tables.contains_key(&key) {
+            let table = self.read_table(first).unwrap_or_default();
+            self.tables.insert(key, table);
+        }
+        key
+    }
+
+    fn read_table(&self, map: &MapRange) -> Option<SymbolTable> {
+        let file = self.procfs.open_module_file(self.pid, map)?;
+        SymbolTable::read(BufReader::new(file)).ok()
+    }
+}
+
+#[test]
+fn test_symbol_table() {
+    let symbol = |name: &str, value, size| Symbol {
+        name: name.to_string(),
+        value,
+        size,
+    };
+    let table = SymbolTable::from_symbols(vec![
+        symbol("_start", 0x1000, 0x20),
+        symbol("main", 0x1040, 0x10),
+        symbol("__libc_main", 0x1040, 0x10),
+        symbol("marker", 0x1060, 0),
+    ]);
+
+    assert_eq!(table.find(0xfff), None);
+    assert_eq!(
+        table.find(0x1000).map(|(s, o)| (&s.name[..], o)),
+        Some(("_start", 0))
+    );
+    assert_eq!(
+        table.find(0x101f).map(|(s, o)| (&s.name[..], o)),
+        Some(("_start", 0x1f))
+    );
+    // Between symbols
+    assert_eq!(table.find(0x1020), None);
+    let (alias, offset) = table.find(0x1044).unwrap();
+    assert!(alias.name == "main" || alias.name == "__libc_main");
+    assert_eq!(offset, 4);
+    assert_eq!(
+        table.find(0x1060).map(|(s, o)| (&s.name[..], o)),
+        Some(("marker", 0))
+    );
+    assert_eq!(table.find(0x1061), None);
+
+    // A label without a size inside a function doesn't hide the function
+    let nested = SymbolTable::from_symbols(vec![
+        symbol("loop", 0x2000, 0x40),
+        symbol(".Lretry", 0x2010, 0),
+        symbol("inner", 0x2020, 0x8),
+        symbol("after", 0x2100, 0x10),
+    ]);
+    assert_eq!(
+        nested.find(0x2010).map(|(s, o)| (&s.name[..], o)),
+        Some((".Lretry", 0))
+    );
+    assert_eq!(
+        nested.find(0x2014).map(|(s, o)| (&s.name[..], o)),
+        Some(("loop", 0x14))
+    );
+    assert_eq!(
+        nested.find(0x2024).map(|(s, o)| (&s.name[..], o)),
+        Some(("inner", 4))
+    );
+    assert_eq!(
+        nested.find(0x2030).map(|(s, o)| (&s.name[..], o)),
+        Some(("loop", 0x30))
+    );
+    assert_eq!(nested.find(0x2040), None);
+    assert_eq!(nested.find(0x2110), None);
+    assert_eq!(nested.max_ends, vec![0x2040, 0x2040, 0x2040, 0x2110]);
+
+    assert_eq!(table.lookup("main").map(|s| s.value), Some(0x1040));
+    assert_eq!(table.lookup("missing"), None);
+
+    // Core dumps have no section headers, so no symbols
+    let core = include_bytes!("../../ci/testdata/core64.elf");
+    let table = SymbolTable::read(std::io::Cursor::new(&core[..])).unwrap();
+    assert!(table.symbols().is_empty());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_resolve_main() {
+    let path = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .with_file_name("test");
+    if !path.exists() {
+        println!("Skipping test because the 'test' binary hasn't been built");
+        return;
+    }
+    // Kills the child even if an assertion fails
+    struct KillOnDrop(std::process::Child);
+    impl Drop for KillOnDrop {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    let child = KillOnDrop(
+        std::process::Command::new(&path)
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to execute test process"),
+    );
+    let pid = child.0.id() as Pid;
+
+    // The child only maps the test binary once it has called exec
+    let mut resolver = None;
+    for _ in 0..50 {
+        let candidate = SymbolResolver::new(pid).unwrap();
+        if candidate.modules().iter().any(|m| m.filename() == path) {
+            resolver = Some(candidate);
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    let mut resolver = resolver.expect("the test binary was never mapped");
+
+    let main = resolver.address_of("main").unwrap();
+    {
+        let (module, symbol, offset) = resolver.resolve(main).unwrap();
+        assert_eq!(module.filename(), path);
+        assert_eq!(symbol.name, "main");
+        assert_eq!(offset, 0);
+
+        // The table read from the file agrees with the loaded address
+        let file = std::fs::File::open(&path).unwrap();
+        let table = SymbolTable::read(BufReader::new(file)).unwrap();
+        let value = table.lookup("main").unwrap().value;
+        assert_eq!(Some(main - value), module.load_bias());
+    }
+    let (_, symbol, offset) = resolver.resolve(main + 1).unwrap();
+    assert_eq!((&symbol.name[..], offset), ("main", 1));
+    let tables = resolver.tables.len();
+    resolver.refresh().unwrap();
+    assert!(resolver.resolve(main).is_some());
+    assert_eq!(resolver.tables.len(), tables);
+    assert!(resolver.resolve(0).is_none());
+}
@@ -0,0 +1,239 @@
+use libc;
+use std;
+use std::collections::HashMap;
+
+use super::{MapRange, ParseError, ParseField, Pid, ProcFs, RegionKind};
+
+/// Gets a Vec of [`MapRange`](struct.MapRange.html) structs from a single
+/// thread's view in `/proc/PID/task/TID/maps`.
+///
+/// This only differs from the process view on kernels between 3.4 and 4.4,
+/// which mark the thread's own stack as `[stack]` here.
+pub fn get_thread_maps(pid: Pid, tid: Pid) -> std::io::Result<Vec<MapRange>> {
+    ProcFs::default().get_thread_maps(pid, tid)
+}
+
+/// Returns the stack region of each thread in the passed in PID, keyed by the
+/// thread's TID.
+///
+/// The main thread's stack is found from the `startstack` field of
+/// `/proc/PID/stat`. Other threads' stacks are found from the `[stack:TID]`
+/// annotations or the per-thread views on kernels that have them, and
+/// otherwise from the stack pointer in `/proc/PID/task/TID/syscall`, which
+/// needs the same permissions as `ptrace`. Threads whose stack can't be found,
+/// or that exit while this runs, are left out.
+///
+/// Thread stacks are anonymous memory, so the kernel can merge adjacent ones
+/// into a single region, in which case several TIDs map to the same
+/// MapRange.
+pub fn get_thread_stacks(pid: Pid) -> std::io::Result<HashMap<Pid, MapRange>> {
+    ProcFs::default().get_thread_stacks(pid)
+}
+
+impl ProcFs {
+    /// Gets the maps of a single thread from this procfs, like
+    /// [`get_thread_maps`](fn.get_thread_maps.html)
+    pub fn get_thread_maps(&self, pid: Pid, tid: Pid) -> std::io::Result<Vec<MapRange>> {
+        let mut contents = Vec::new();
+        self.read_bytes(pid, &format!("task/{}/maps", tid), &mut contents)?;
+        super::parse_proc_maps(&contents)
+    }
+
+    /// Returns the stack region of each thread in the passed in PID from this
+    /// procfs, like [`get_thread_stacks`](fn.get_thread_stacks.html)
+    pub fn get_thread_stacks(&self, pid: Pid) -> std::io::Result<HashMap<Pid, MapRange>> {
+        let maps = self.get_process_maps(pid)?;
+        let find = |addr: usize| {
+            maps.iter()
+                .find(|m| m.start() <= addr && addr - m.start() < m.size())
+        };
+
+        // startstack is the same in every thread's stat file
+        let start_stack = self.read_start_stack(pid)?;
+        let holds_start_stack =
+            |m: &MapRange| m.start() <= start_stack && start_stack - m.start() < m.size();
+
+        let mut stacks = HashMap::new();
+        // Whether the per-thread views mark each thread's own stack, which is
+        // only checked until one of them shows that they don't
+        let mut thread_views = true;
+        for tid in self.get_thread_ids(pid)? {
+            if let Some(map) = maps
+                .iter()
+                .find(|m| m.kind() == RegionKind::ThreadStack(tid as u32))
+            {
+                stacks.insert(tid, map.clone());
+                continue;
+            }
+            if tid == pid {
+                if let Some(map) = find(start_stack) {
+                    stacks.insert(tid, map.clone());
+                }
+                continue;
+            }
+
+            if thread_views {
+                let view = match self.get_thread_maps(pid, tid) {
+                    Ok(view) => view,
+                    Err(ref e) if is_thread_gone(e) => continue,
+                    Err(e) => return Err(e),
+                };
+                match view.into_iter().find(|m| m.kind() == RegionKind::Stack) {
+                    Some(ref map) if holds_start_stack(map) => thread_views = false,
+                    Some(map) => {
+                        stacks.insert(tid, map);
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+
+            match self.read_stack_pointer(pid, tid) {
+                Ok(Some(sp)) => {
+                    if let Some(map) = find(sp) {
+                        stacks.insert(tid, map.clone());
+                    }
+                }
+                Ok(None) => {}
+                Err(ref e) if is_thread_gone(e) || is_permission_error(e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(stacks)
+    }
+
+    /// Lists the TIDs of the threads in the passed in PID
+    fn get_thread_ids(&self, pid: Pid) -> std::io::Result<Vec<Pid>> {
+        let mut tids = Vec::new();
+        for entry in std::fs::read_dir(self.path(pid, "task"))? {
+            if let Some(tid) = entry?.file_name().to_str().and_then(|s| s.parse().ok()) {
+                tids.push(tid);
+            }
+        }
+        tids.sort();
+        Ok(tids)
+    }
+
+    fn read_start_stack(&self, pid: Pid) -> std::io::Result<usize> {
+        let stat = self.read_string(pid, "stat")?;
+        match parse_start_stack(&stat) {
+            Some(addr) => Ok(addr),
+            None => Err(ParseError::new(ParseField::Field, stat.trim_end().as_bytes()).into()),
+        }
+    }
+
+    fn read_stack_pointer(&self, pid: Pid, tid: Pid) -> std::io::Result<Option<usize>> {
+        let syscall = self.read_string(pid, &format!("task/{}/syscall", tid))?;
+        Ok(parse_stack_pointer(&syscall))
+    }
+}
+
+/// Returns the `startstack` field of a `stat` file
+fn parse_start_stack(stat: &str) -> Option<usize> {
+    // The command name can contain spaces and parentheses, so the fields are
+    // counted from the last closing parenthesis, which is followed by the
+    // third field
+    let fields = &stat[stat.rfind(')')? + 1..];
+    fields.split_whitespace().nth(28 - 3)?.parse().ok()
+}
+
+/// Returns the stack pointer from a `syscall` file, which is `running` for
+/// threads that are on a CPU, and otherwise ends with the stack pointer and
+/// program counter
+fn parse_stack_pointer(syscall: &str) -> Option<usize> {
+    let fields: Vec<&str> = syscall.split_whitespace().collect();
+    if fields.len() < 3 {
+        return None;
+    }
+    let sp = fields[fields.len() - 2];
+    usize::from_str_radix(sp.trim_start_matches("0x"), 16).ok()
+}
+
+fn is_thread_gone(err: &std::io::Error) -> bool {
+    err.kind() == std::io::ErrorKind::NotFound || err.raw_os_error() == Some(libc::ESRCH)
+}
+
+fn is_permission_error(err: &std::io::Error) -> bool {
+    err.kind() == std::io::ErrorKind::PermissionDenied
+}
+
+#[test]
+fn test_parse_thread_files() {
+    let stat = "1234 (a) b (c)) S 1 1234 1234 0 -1 4194560 100 0 0 0 1 2 0 0 20 0 3 0 \
+                500 10000000 200 18446744073709551615 1 1 140732800000000 0 0 0 0 0 0 0 \
+                0 0 17 3 0 0 0 0 0";
+    assert_eq!(parse_start_stack(stat), Some(140732800000000));
+    assert_eq!(parse_start_stack("1234 (truncated"), None);
+
+    assert_eq!(
+        parse_stack_pointer(
+            "202 0x7f0000000ba0 0x189 0x0 0x0 0x0 0xffffffff 0x7f00000078c0 0x7f0000003f16\n"
+        ),
+        Some(0x7f00000078c0)
+    );
+    assert_eq!(
+        parse_stack_pointer("-1 0x7ffe00001000 0x7f0000003f16\n"),
+        Some(0x7ffe00001000)
+    );
+    assert_eq!(parse_stack_pointer("running\n"), None);
+}
+
+#[test]
+fn test_get_thread_stacks() {
+    use std::sync::mpsc::channel;
+
+    let pid = std::process::id() as Pid;
+    let (started_tx, started_rx) = channel();
+    let mut threads = Vec::new();
+    let mut stops = Vec::new();
+    for _ in 0..3 {
+        let started_tx = started_tx.clone();
+        let (stop_tx, stop_rx) = channel::<()>();
+        stops.push(stop_tx);
+        threads.push(std::thread::spawn(move || {
+            let local = 0u8;
+            let tid = unsafe { libc::syscall(libc::SYS_gettid) } as Pid;
+            started_tx
+                .send((tid, &local as *const u8 as usize))
+                .unwrap();
+            let _ = stop_rx.recv();
+        }));
+    }
+    let started: Vec<(Pid, usize)> = (0..3).map(|_| started_rx.recv().unwrap()).collect();
+    // A thread's stack pointer can only be read once it is blocked in recv,
+    // rather than still on its way there after sending its TID
+    let procfs = ProcFs::default();
+    for &(tid, _) in started.iter() {
+        loop {
+            match procfs.read_stack_pointer(pid, tid) {
+                Ok(Some(_)) => break,
+                Ok(None) => std::thread::yield_now(),
+                // Without the syscall file, or the access to read it, there's
+                // no other way to find the stack on current kernels
+                Err(e) => {
+                    println!(
+                        "Skipping test because the stack pointer can't be read: {}",
+                        e
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    let stacks = get_thread_stacks(pid).unwrap();
+    assert_eq!(stacks[&pid].kind(), RegionKind::Stack);
+    for &(tid, local) in started.iter() {
+        let stack = stacks
+            .get(&tid)
+            .unwrap_or_else(|| panic!("no stack found for thread {}", tid));
+        assert!(stack.start() <= local && local < stack.start() + stack.size());
+    }
+
+    drop(stops);
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert!(!get_thread_maps(pid, pid).unwrap().is_empty());
+}
@@ -0,0 +1,225 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+
+/// The kernel's two letter mnemonics for each flag, in the order that
+/// `/proc/PID/smaps` prints them. Flag `i` in this list has bit `1 << i`.
+const MNEMONICS: [&str; 41] = [
+    "rd", "wr", "ex", "sh", "mr", "mw", "me", "ms", "gd", "pf", "dw", "lo", "io", "sr", "rr", "dc",
+    "de", "lf", "ac", "nr", "ht", "sf", "nl", "ar", "wf", "dd", "bt", "mp", "sd", "mm", "hg", "nh",
+    "mg", "um", "uw", "ui", "mt", "ss", "sl", "dp", "gu",
+];
+
+/// The `VmFlags:` line of a region in `/proc/PID/smaps`.
+///
+/// Each known flag has a constant, which can be combined with `|` and tested
+/// with [`contains`](#method.contains). The set of flags changes between kernel
+/// versions, so mnemonics that this crate doesn't know about are kept and can
+/// be found with [`unknown`](#method.unknown).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct VmFlags {
+    bits: u64,
+    unknown: Vec<String>,
+}
+
+impl VmFlags {
+    /// `rd`: readable
+    pub const READ: VmFlags = VmFlags::from_bits(1 << 0);
+    /// `wr`: writeable
+    pub const WRITE: VmFlags = VmFlags::from_bits(1 << 1);
+    /// `ex`: executable
+    pub const EXEC: VmFlags = VmFlags::from_bits(1 << 2);
+    /// `sh`: shared
+    pub const SHARED: VmFlags = VmFlags::from_bits(1 << 3);
+    /// `mr`: may read
+    pub const MAY_READ: VmFlags = VmFlags::from_bits(1 << 4);
+    /// `mw`: may write
+    pub const MAY_WRITE: VmFlags = VmFlags::from_bits(1 << 5);
+    /// `me`: may execute
+    pub const MAY_EXEC: VmFlags = VmFlags::from_bits(1 << 6);
+    /// `ms`: may share
+    pub const MAY_SHARE: VmFlags = VmFlags::from_bits(1 << 7);
+    /// `gd`: stack segment grows down
+    pub const GROWS_DOWN: VmFlags = VmFlags::from_bits(1 << 8);
+    /// `pf`: pure PFN range
+    pub const PFN_MAP: VmFlags = VmFlags::from_bits(1 << 9);
+    /// `dw`: disabled write to the mapped file (removed in Linux 6.1)
+    pub const DENY_WRITE: VmFlags = VmFlags::from_bits(1 << 10);
+    /// `lo`: pages are locked in memory
+    pub const LOCKED: VmFlags = VmFlags::from_bits(1 << 11);
+    /// `io`: memory mapped I/O area
+    pub const IO: VmFlags = VmFlags::from_bits(1 << 12);
+    /// `sr`: sequential read advise provided
+    pub const SEQ_READ: VmFlags = VmFlags::from_bits(1 << 13);
+    /// `rr`: random read advise provided
+    pub const RAND_READ: VmFlags = VmFlags::from_bits(1 << 14);
+    /// `dc`: do not copy area on fork
+    pub const DONT_COPY: VmFlags = VmFlags::from_bits(1 << 15);
+    /// `de`: do not expand area on remapping
+    pub const DONT_EXPAND: VmFlags = VmFlags::from_bits(1 << 16);
+    /// `lf`: lock on fault pages
+    pub const LOCK_ON_FAULT: VmFlags = VmFlags::from_bits(1 << 17);
+    /// `ac`: area is accountable
+    pub const ACCOUNT: VmFlags = VmFlags::from_bits(1 << 18);
+    /// `nr`: swap space is not reserved for the area
+    pub const NO_RESERVE: VmFlags = VmFlags::from_bits(1 << 19);
+    /// `ht`: area uses huge tlb pages
+    pub const HUGETLB: VmFlags = VmFlags::from_bits(1 << 20);
+    /// `sf`: synchronous page fault
+    pub const SYNC: VmFlags = VmFlags::from_bits(1 << 21);
+    /// `nl`: non-linear mapping (removed in Linux 4.0)
+    pub const NON_LINEAR: VmFlags = VmFlags::from_bits(1 << 22);
+    /// `ar`: architecture specific flag
+    pub const ARCH_1: VmFlags = VmFlags::from_bits(1 << 23);
+    /// `wf`: wipe on fork
+    pub const WIPE_ON_FORK: VmFlags = VmFlags::from_bits(1 << 24);
+    /// `dd`: do not include area into core dump
+    pub const DONT_DUMP: VmFlags = VmFlags::from_bits(1 << 25);
+    /// `bt`: arm64 BTI guarded page
+    pub const ARM64_BTI: VmFlags = VmFlags::from_bits(1 << 26);
+    /// `mp`: MPX bounds table (removed in Linux 5.6)
+    pub const MPX: VmFlags = VmFlags::from_bits(1 << 27);
+    /// `sd`: soft dirty flag
+    pub const SOFT_DIRTY: VmFlags = VmFlags::from_bits(1 << 28);
+    /// `mm`: mixed map area
+    pub const MIXED_MAP: VmFlags = VmFlags::from_bits(1 << 29);
+    /// `hg`: huge page advise flag
+    pub const HUGEPAGE: VmFlags = VmFlags::from_bits(1 << 30);
+    /// `nh`: no huge page advise flag
+    pub const NO_HUGEPAGE: VmFlags = VmFlags::from_bits(1 << 31);
+    /// `mg`: mergeable advise flag
+    pub const MERGEABLE: VmFlags = VmFlags::from_bits(1 << 32);
+    /// `um`: userfaultfd missing tracking
+    pub const UFFD_MISSING: VmFlags = VmFlags::from_bits(1 << 33);
+    /// `uw`: userfaultfd wr-protect tracking
+    pub const UFFD_WP: VmFlags = VmFlags::from_bits(1 << 34);
+    /// `ui`: userfaultfd minor fault
+    pub const UFFD_MINOR: VmFlags = VmFlags::from_bits(1 << 35);
+    /// `mt`: arm64 MTE allocation tags are enabled
+    pub const MTE: VmFlags = VmFlags::from_bits(1 << 36);
+    /// `ss`: shadow stack page
+    pub const SHADOW_STACK: VmFlags = VmFlags::from_bits(1 << 37);
+    /// `sl`: sealed
+    pub const SEALED: VmFlags = VmFlags::from_bits(1 << 38);
+    /// `dp`: always lazily freeable mapping
+    pub const DROPPABLE: VmFlags = VmFlags::from_bits(1 << 39);
+    /// `gu`: area has guard regions installed
+    pub const GUARD_REGIONS: VmFlags = VmFlags::from_bits(1 << 40);
+
+    const fn from_bits(bits: u64) -> VmFlags {
+        VmFlags {
+            bits,
+            unknown: Vec::new(),
+        }
+    }
+
+    /// Parses the space separated mnemonics of a `VmFlags:` line
+    pub fn parse(s: &str) -> VmFlags {
+        let mut flags = VmFlags::default();
+        for mnemonic in s.split_whitespace() {
+            match MNEMONICS.iter().position(|&m| m == mnemonic) {
+                Some(i) => flags.bits |= 1 << i,
+                None => flags.unknown.push(mnemonic.to_string()),
+            }
+        }
+        flags
+    }
+
+    /// Returns the bits of the known flags that are set
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    /// Returns whether every flag set in `other` is also set here, including
+    /// any unknown mnemonics
+    pub fn contains(&self, other: VmFlags) -> bool {
+        self.bits & other.bits == other.bits
+            && other.unknown.iter().all(|m| self.unknown.contains(m))
+    }
+
+    /// Returns whether no flags are set
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0 && self.unknown.is_empty()
+    }
+
+    /// Returns the mnemonics that this crate doesn't know about, in the order
+    /// that the kernel printed them
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
+    }
+}
+
+impl BitOr for VmFlags {
+    type Output = VmFlags;
+
+    fn bitor(mut self, rhs: VmFlags) -> VmFlags {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for VmFlags {
+    fn bitor_assign(&mut self, rhs: VmFlags) {
+        self.bits |= rhs.bits;
+        for mnemonic in rhs.unknown {
+            if !self.unknown.contains(&mnemonic) {
+                self.unknown.push(mnemonic);
+            }
+        }
+    }
+}
+
+impl fmt::Display for VmFlags {
+    /// Writes the flags back out as space separated mnemonics
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let known = MNEMONICS
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.bits & (1 << i) != 0)
+            .map(|(_, m)| *m);
+        let unknown = self.unknown.iter().map(|m| m.as_str());
+        for (i, mnemonic) in known.chain(unknown).enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(mnemonic)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes as the space separated mnemonics, like `"rd wr mr mw me ac"`,
+/// since the bit assigned to each flag is private to this crate
+#[cfg(feature = "serde")]
+impl Serialize for VmFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for VmFlags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<VmFlags, D::Error> {
+        let mnemonics = String::deserialize(deserializer)?;
+        Ok(VmFlags::parse(&mnemonics))
+    }
+}
+
+#[test]
+fn test_parse_vm_flags() {
+    let flags = VmFlags::parse("rd wr mr mw me lo ac sd ss zz ");
+    assert!(flags.contains(VmFlags::READ | VmFlags::WRITE));
+    assert!(flags.contains(VmFlags::LOCKED));
+    assert!(flags.contains(VmFlags::SHADOW_STACK));
+    assert!(!flags.contains(VmFlags::EXEC));
+    assert!(!flags.contains(VmFlags::DONT_DUMP));
+    assert_eq!(flags.unknown(), &["zz".to_string()]);
+    assert_eq!(flags.to_string(), "rd wr mr mw me lo ac sd ss zz");
+
+    assert!(VmFlags::parse("").is_empty());
+    assert_eq!(
+        VmFlags::parse("hg dd mt"),
+        VmFlags::HUGEPAGE | VmFlags::DONT_DUMP | VmFlags::MTE
+    );
+}
@@ -14,6 +14,7 @@ use std::mem;
 use std::path::{Path, PathBuf};
 
 use MapRangeImpl;
+use Permissions;
 
 mod dyld_bindings;
 use self::dyld_bindings::{
@@ -23,6 +24,11 @@ use self::dyld_bindings::{
 pub type Pid = pid_t;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(from = "MapRangeSchema", into = "MapRangeSchema")
+)]
 pub struct MapRange {
     size: mach_vm_size_t,
     info: vm_region_basic_info_data_64_t,
@@ -32,6 +38,66 @@ pub struct MapRange {
     filename: Option<PathBuf>,
 }
 
+/// The serialized form of a MapRange, which flattens out the fields of the
+/// `vm_region_basic_info_64` that it was read from
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct MapRangeSchema {
+    start: mach_vm_address_t,
+    size: mach_vm_size_t,
+    filename: Option<PathBuf>,
+    protection: mach2::vm_prot::vm_prot_t,
+    max_protection: mach2::vm_prot::vm_prot_t,
+    inheritance: mach2::vm_inherit::vm_inherit_t,
+    shared: bool,
+    reserved: bool,
+    offset: mach2::memory_object_types::memory_object_offset_t,
+    behavior: mach2::vm_behavior::vm_behavior_t,
+    user_wired_count: ::libc::c_ushort,
+}
+
+#[cfg(feature = "serde")]
+impl From<MapRange> for MapRangeSchema {
+    fn from(map: MapRange) -> MapRangeSchema {
+        let info = map.info;
+        MapRangeSchema {
+            start: map.start,
+            size: map.size,
+            filename: map.filename,
+            protection: info.protection,
+            max_protection: info.max_protection,
+            inheritance: info.inheritance,
+            shared: info.shared != 0,
+            reserved: info.reserved != 0,
+            offset: info.offset,
+            behavior: info.behavior,
+            user_wired_count: info.user_wired_count,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<MapRangeSchema> for MapRange {
+    fn from(schema: MapRangeSchema) -> MapRange {
+        MapRange {
+            size: schema.size,
+            info: vm_region_basic_info_data_64_t {
+                protection: schema.protection,
+                max_protection: schema.max_protection,
+                inheritance: schema.inheritance,
+                shared: schema.shared as mach2::boolean::boolean_t,
+                reserved: schema.reserved as mach2::boolean::boolean_t,
+                offset: schema.offset,
+                behavior: schema.behavior,
+                user_wired_count: schema.user_wired_count,
+            },
+            start: schema.start,
+            count: vm_region_basic_info_data_64_t::count(),
+            filename: schema.filename,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Symbol {
     pub value: Option<usize>,
@@ -89,6 +155,14 @@ impl MapRangeImpl for MapRange {
     fn is_read(&self) -> bool {
         self.info.protection & mach2::vm_prot::VM_PROT_READ != 0
     }
+    fn permissions(&self) -> Permissions {
+        Permissions::new(
+            self.is_read(),
+            self.is_write(),
+            self.is_exec(),
+            self.info.shared != 0,
+        )
+    }
 }
 
 impl MapRange {
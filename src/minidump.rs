@@ -0,0 +1,378 @@
+//! Read the memory layout of a process from a Breakpad or Crashpad minidump.
+//!
+//! This is a pure file parser, so minidumps written on any operating system
+//! can be read on every platform.
+
+use std;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use elf::{invalid_data, read_u32, read_u64};
+use linux_maps::{self, MapRange as LinuxMapRange};
+use MapRangeImpl;
+use Permissions;
+
+const MINIDUMP_SIGNATURE: u32 = 0x504d_444d;
+
+const MODULE_LIST_STREAM: u32 = 4;
+const MEMORY_INFO_LIST_STREAM: u32 = 16;
+/// The contents of `/proc/PID/maps`, written by Breakpad and Crashpad on Linux
+/// and Android
+const MD_LINUX_MAPS: u32 = 0x4767_0009;
+
+const MODULE_SIZE: usize = 108;
+const MEMORY_INFO_SIZE: usize = 48;
+
+const MEM_COMMIT: u32 = 0x1000;
+const MEM_MAPPED: u32 = 0x40000;
+const MEM_IMAGE: u32 = 0x100_0000;
+
+const PAGE_READONLY: u32 = 0x02;
+const PAGE_READWRITE: u32 = 0x04;
+const PAGE_WRITECOPY: u32 = 0x08;
+const PAGE_EXECUTE: u32 = 0x10;
+const PAGE_EXECUTE_READ: u32 = 0x20;
+const PAGE_EXECUTE_READWRITE: u32 = 0x40;
+const PAGE_EXECUTE_WRITECOPY: u32 = 0x80;
+
+/// The largest stream that will be read into memory, so that a corrupt
+/// directory can't cause a huge allocation
+const MAX_STREAM_SIZE: u32 = 0x10000000;
+
+/// A single memory region from a minidump.
+///
+/// This has the same methods as [`MapRange`](../struct.MapRange.html).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MinidumpRegion {
+    range_start: usize,
+    range_size: usize,
+    pathname: Option<PathBuf>,
+    perms: Permissions,
+}
+
+impl MapRangeImpl for MinidumpRegion {
+    fn size(&self) -> usize {
+        self.range_size
+    }
+    fn start(&self) -> usize {
+        self.range_start
+    }
+    fn filename(&self) -> Option<&Path> {
+        self.pathname.as_deref()
+    }
+    fn is_exec(&self) -> bool {
+        self.perms.is_exec()
+    }
+    fn is_write(&self) -> bool {
+        self.perms.is_write()
+    }
+    fn is_read(&self) -> bool {
+        self.perms.is_read()
+    }
+    fn permissions(&self) -> Permissions {
+        self.perms
+    }
+}
+
+impl From<&LinuxMapRange> for MinidumpRegion {
+    fn from(map: &LinuxMapRange) -> MinidumpRegion {
+        MinidumpRegion {
+            range_start: map.start(),
+            range_size: map.size(),
+            pathname: map.filename().map(PathBuf::from),
+            perms: map.permissions(),
+        }
+    }
+}
+
+/// A module from the minidump's module list
+struct Module {
+    base_addr: u64,
+    base_size: u64,
+    pathname: PathBuf,
+}
+
+/// Reads the memory regions of the process that a minidump was written for.
+///
+/// See [`parse_minidump`](fn.parse_minidump.html) for the details.
+pub fn get_minidump_regions<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<MinidumpRegion>> {
+    parse_minidump(BufReader::new(File::open(path)?))
+}
+
+/// Reads the memory regions of the process that a minidump was written for
+/// from `reader`.
+///
+/// The regions come from the first of these streams that the minidump has:
+///
+/// * `MD_LINUX_MAPS`, the process's `/proc/PID/maps`, which Linux and Android
+///   minidumps include.
+/// * `MemoryInfoListStream`, which lists every committed region with its
+///   protection. Regions in an executable image get the pathname of the
+///   module from `ModuleListStream` that contains them.
+/// * `ModuleListStream` alone, with one region per module. Minidumps don't
+///   record the protection of modules, so these regions have no permissions.
+pub fn parse_minidump<R: Read + Seek>(mut reader: R) -> std::io::Result<Vec<MinidumpRegion>> {
+    let header = read_at(&mut reader, 0, 32)?;
+    if read_u32(&header, 0) != MINIDUMP_SIGNATURE {
+        return Err(invalid_data("not a minidump"));
+    }
+    let stream_count = read_u32(&header, 8);
+    let directory_rva = read_u32(&header, 12);
+    let directory = read_at(&mut reader, directory_rva, stream_count.saturating_mul(12))?;
+    let streams: Vec<(u32, u32, u32)> = directory
+        .chunks_exact(12)
+        .map(|entry| (read_u32(entry, 0), read_u32(entry, 4), read_u32(entry, 8)))
+        .collect();
+
+    if let Some(maps) = read_stream(&mut reader, &streams, MD_LINUX_MAPS)? {
+        return Ok(linux_maps::parse_maps_from_reader(&maps[..])?
+            .iter()
+            .map(MinidumpRegion::from)
+            .collect());
+    }
+
+    let modules = match read_stream(&mut reader, &streams, MODULE_LIST_STREAM)? {
+        Some(stream) => parse_module_list(&stream, &mut reader)?,
+        None => Vec::new(),
+    };
+    match read_stream(&mut reader, &streams, MEMORY_INFO_LIST_STREAM)? {
+        Some(stream) => parse_memory_info_list(&stream, &modules),
+        None => modules
+            .iter()
+            .map(|module| {
+                Ok(MinidumpRegion {
+                    range_start: to_usize(module.base_addr)?,
+                    range_size: to_usize(module.base_size)?,
+                    pathname: Some(module.pathname.clone()),
+                    perms: Permissions::default(),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Reads the first stream of the passed in type, given the `(type, size, rva)`
+/// entries of the stream directory
+fn read_stream<R: Read + Seek>(
+    reader: &mut R,
+    streams: &[(u32, u32, u32)],
+    stream_type: u32,
+) -> std::io::Result<Option<Vec<u8>>> {
+    match streams.iter().find(|s| s.0 == stream_type) {
+        Some(&(_, size, rva)) => Ok(Some(read_at(reader, rva, size)?)),
+        None => Ok(None),
+    }
+}
+
+fn parse_module_list<R: Read + Seek>(
+    stream: &[u8],
+    reader: &mut R,
+) -> std::io::Result<Vec<Module>> {
+    let truncated = || invalid_data("truncated module list");
+    if stream.len() < 4 {
+        return Err(truncated());
+    }
+    let count = read_u32(stream, 0) as usize;
+    let entries = stream
+        .get(4..)
+        .and_then(|entries| entries.get(..count.checked_mul(MODULE_SIZE)?))
+        .ok_or_else(truncated)?;
+
+    let mut modules = Vec::with_capacity(count);
+    for entry in entries.chunks_exact(MODULE_SIZE) {
+        modules.push(Module {
+            base_addr: read_u64(entry, 0),
+            base_size: read_u32(entry, 8) as u64,
+            pathname: PathBuf::from(read_string(reader, read_u32(entry, 20))?),
+        });
+    }
+    modules.sort_by_key(|m| m.base_addr);
+    Ok(modules)
+}
+
+/// Reads a `MINIDUMP_STRING`, which is a byte length followed by UTF-16
+fn read_string<R: Read + Seek>(reader: &mut R, rva: u32) -> std::io::Result<String> {
+    let length = read_u32(&read_at(reader, rva, 4)?, 0);
+    let bytes = read_at(reader, rva.saturating_add(4), length)?;
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+fn parse_memory_info_list(
+    stream: &[u8],
+    modules: &[Module],
+) -> std::io::Result<Vec<MinidumpRegion>> {
+    let truncated = || invalid_data("truncated memory info list");
+    if stream.len() < 16 {
+        return Err(truncated());
+    }
+    let header_size = read_u32(stream, 0) as usize;
+    let entry_size = read_u32(stream, 4) as usize;
+    let count = read_u64(stream, 8) as usize;
+    if entry_size < MEMORY_INFO_SIZE {
+        return Err(invalid_data("invalid memory info size"));
+    }
+    let entries = stream
+        .get(header_size..)
+        .and_then(|entries| entries.get(..count.checked_mul(entry_size)?))
+        .ok_or_else(truncated)?;
+
+    let mut regions = Vec::new();
+    for entry in entries.chunks_exact(entry_size) {
+        let base_addr = read_u64(entry, 0);
+        let base_size = read_u64(entry, 24);
+        let state = read_u32(entry, 32);
+        let protect = read_u32(entry, 36);
+        let region_type = read_u32(entry, 40);
+
+        // Skip free pages and pages that are reserved but not allocated
+        if state & MEM_COMMIT == 0 {
+            continue;
+        }
+        let pathname = if region_type & MEM_IMAGE != 0 {
+            find_module(modules, base_addr, base_size).map(|m| m.pathname.clone())
+        } else {
+            None
+        };
+        regions.push(MinidumpRegion {
+            range_start: to_usize(base_addr)?,
+            range_size: to_usize(base_size)?,
+            pathname,
+            perms: Permissions::new(
+                protect
+                    & (PAGE_EXECUTE_READ
+                        | PAGE_EXECUTE_READWRITE
+                        | PAGE_EXECUTE_WRITECOPY
+                        | PAGE_READONLY
+                        | PAGE_READWRITE
+                        | PAGE_WRITECOPY)
+                    != 0,
+                protect & (PAGE_EXECUTE_READWRITE | PAGE_READWRITE) != 0,
+                protect
+                    & (PAGE_EXECUTE
+                        | PAGE_EXECUTE_READ
+                        | PAGE_EXECUTE_READWRITE
+                        | PAGE_EXECUTE_WRITECOPY)
+                    != 0,
+                // Views of a section are shared unless they were mapped
+                // copy-on-write
+                region_type & MEM_MAPPED != 0
+                    && protect & (PAGE_WRITECOPY | PAGE_EXECUTE_WRITECOPY) == 0,
+            ),
+        });
+    }
+    Ok(regions)
+}
+
+/// Finds the module that contains a region, given modules that are sorted by
+/// base address and don't overlap
+fn find_module(modules: &[Module], base_addr: u64, base_size: u64) -> Option<&Module> {
+    let module = match modules.binary_search_by_key(&base_addr, |m| m.base_addr) {
+        Ok(i) => &modules[i],
+        Err(0) => return None,
+        Err(i) => &modules[i - 1],
+    };
+    let module_end = module.base_addr.checked_add(module.base_size)?;
+    if base_addr.checked_add(base_size)? <= module_end {
+        Some(module)
+    } else {
+        None
+    }
+}
+
+/// Reads `len` bytes starting at `rva`, failing if the file is shorter
+fn read_at<R: Read + Seek>(reader: &mut R, rva: u32, len: u32) -> std::io::Result<Vec<u8>> {
+    if len > MAX_STREAM_SIZE {
+        return Err(invalid_data("minidump stream is too large"));
+    }
+    let mut buffer = Vec::new();
+    reader.seek(SeekFrom::Start(rva as u64))?;
+    reader.take(len as u64).read_to_end(&mut buffer)?;
+    if buffer.len() < len as usize {
+        return Err(invalid_data("minidump is truncated"));
+    }
+    Ok(buffer)
+}
+
+fn to_usize(value: u64) -> std::io::Result<usize> {
+    if value > usize::MAX as u64 {
+        return Err(invalid_data("address doesn't fit in usize"));
+    }
+    Ok(value as usize)
+}
+
+#[cfg(test)]
+fn parse(data: &[u8]) -> Vec<MinidumpRegion> {
+    parse_minidump(std::io::Cursor::new(data)).unwrap()
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_windows_minidump() {
+    let regions = parse(include_bytes!("../ci/testdata/minidump_windows.dmp"));
+    // The free and reserved regions are skipped
+    assert_eq!(regions.len(), 6);
+
+    assert_eq!(regions[0].start(), 0x20000);
+    assert_eq!(regions[0].filename(), None);
+    assert_eq!(
+        regions[0].permissions(),
+        Permissions::new(true, true, false, true)
+    );
+    assert!(regions[1].is_write());
+    assert!(regions[1].permissions().is_private());
+
+    let app = Some(Path::new("C:\\app\\app.exe"));
+    assert_eq!(regions[2].filename(), app);
+    assert!(regions[2].is_read() && !regions[2].is_exec());
+    assert_eq!(regions[3].filename(), app);
+    assert_eq!(regions[3].size(), 0x2000);
+    assert!(regions[3].is_exec() && !regions[3].is_write());
+    assert_eq!(regions[4].filename(), app);
+    assert!(regions[4].is_read() && !regions[4].is_write());
+    assert_eq!(
+        regions[5].filename(),
+        Some(Path::new("C:\\Windows\\System32\\kernel32.dll"))
+    );
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_linux_minidump() {
+    let regions = parse(include_bytes!("../ci/testdata/minidump_linux.dmp"));
+    assert_eq!(regions.len(), 5);
+    assert_eq!(regions[0].start(), 0x55d0c1e00000);
+    assert_eq!(regions[0].filename(), Some(Path::new("/usr/bin/sleep")));
+    assert!(regions[1].is_exec());
+    assert_eq!(regions[2].filename(), Some(Path::new("[heap]")));
+    assert_eq!(regions[3].filename(), Some(Path::new("/memfd:shm")));
+    assert!(regions[3].permissions().is_shared());
+    assert_eq!(regions[4].size(), 0x21000);
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_module_list_minidump() {
+    let regions = parse(include_bytes!("../ci/testdata/minidump_modules.dmp"));
+    assert_eq!(regions.len(), 2);
+    assert_eq!(regions[0].start(), 0x7ff6a0000000);
+    assert_eq!(regions[0].size(), 0x5000);
+    assert_eq!(regions[0].filename(), Some(Path::new("C:\\app\\app.exe")));
+    assert_eq!(regions[0].permissions(), Permissions::default());
+}
+
+#[test]
+fn test_invalid_minidump() {
+    assert!(parse_minidump(std::io::Cursor::new(&b"MDMP"[..])).is_err());
+    let core = include_bytes!("../ci/testdata/core64.elf");
+    assert!(parse_minidump(std::io::Cursor::new(&core[..])).is_err());
+
+    let mut truncated = include_bytes!("../ci/testdata/minidump_windows.dmp").to_vec();
+    truncated.truncate(200);
+    assert!(parse_minidump(std::io::Cursor::new(truncated)).is_err());
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use std::str::FromStr;
+
+use linux_maps::{ParseError, ParseField};
+
+/// The access permissions of a memory region.
+///
+/// This parses from, and displays as, the four character `rwxp` column of
+/// `/proc/PID/maps`, where the last character is `s` for shared mappings and
+/// `p` for private (copy-on-write) ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Permissions {
+    read: bool,
+    write: bool,
+    exec: bool,
+    shared: bool,
+}
+
+impl Permissions {
+    /// Creates a Permissions from the readable, writeable, executable and
+    /// shared flags
+    pub fn new(read: bool, write: bool, exec: bool, shared: bool) -> Permissions {
+        Permissions {
+            read,
+            write,
+            exec,
+            shared,
+        }
+    }
+    /// Returns whether the region is readable
+    pub fn is_read(&self) -> bool {
+        self.read
+    }
+    /// Returns whether the region is writeable
+    pub fn is_write(&self) -> bool {
+        self.write
+    }
+    /// Returns whether the region is executable
+    pub fn is_exec(&self) -> bool {
+        self.exec
+    }
+    /// Returns whether writes to the region are shared with other mappings of
+    /// the same memory
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+    /// Returns whether the region is private (copy-on-write) to this process
+    pub fn is_private(&self) -> bool {
+        !self.shared
+    }
+}
+
+impl FromStr for Permissions {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Permissions, ParseError> {
+        let invalid = || ParseError::new(ParseField::Perms, s.as_bytes());
+        let flag = |c: u8, set: u8| match c {
+            b'-' => Ok(false),
+            c if c == set => Ok(true),
+            _ => Err(invalid()),
+        };
+        match s.as_bytes() {
+            &[r, w, x, s] => Ok(Permissions {
+                read: flag(r, b'r')?,
+                write: flag(w, b'w')?,
+                exec: flag(x, b'x')?,
+                shared: match s {
+                    b's' => true,
+                    b'p' => false,
+                    _ => return Err(invalid()),
+                },
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}",
+            if self.read { 'r' } else { '-' },
+            if self.write { 'w' } else { '-' },
+            if self.exec { 'x' } else { '-' },
+            if self.shared { 's' } else { 'p' }
+        )
+    }
+}
+
+#[test]
+fn test_parse_permissions() {
+    let perms: Permissions = "r-xp".parse().unwrap();
+    assert!(perms.is_read());
+    assert!(!perms.is_write());
+    assert!(perms.is_exec());
+    assert!(perms.is_private());
+    assert_eq!(perms.to_string(), "r-xp");
+
+    let perms: Permissions = "rw-s".parse().unwrap();
+    assert_eq!(perms, Permissions::new(true, true, false, true));
+    assert!(perms.is_shared());
+    assert_eq!(perms.to_string(), "rw-s");
+
+    assert_eq!(
+        "---p".parse::<Permissions>().unwrap(),
+        Permissions::default()
+    );
+    assert!("rwx".parse::<Permissions>().is_err());
+    assert!("rwxq".parse::<Permissions>().is_err());
+    assert!("wr-p".parse::<Permissions>().is_err());
+    let err = "rwxq".parse::<Permissions>().unwrap_err();
+    assert_eq!(err.field(), ParseField::Perms);
+    assert_eq!(err.text(), "rwxq");
+}
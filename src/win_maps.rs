@@ -15,15 +15,17 @@ use winapi::um::sysinfoapi::{GetSystemInfo, SYSTEM_INFO};
 use winapi::um::tlhelp32::{CreateToolhelp32Snapshot, TH32CS_SNAPMODULE, TH32CS_SNAPMODULE32};
 use winapi::um::tlhelp32::{Module32FirstW, Module32NextW, MODULEENTRY32W};
 use winapi::um::winnt::{HANDLE, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ};
-use winapi::um::winnt::{MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_IMAGE};
+use winapi::um::winnt::{MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_IMAGE, MEM_MAPPED};
 use winapi::um::winnt::{PAGE_EXECUTE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE};
 use winapi::um::winnt::{PAGE_EXECUTE_WRITECOPY, PAGE_READONLY, PAGE_READWRITE, PAGE_WRITECOPY};
 
 use MapRangeImpl;
+use Permissions;
 
 pub type Pid = u32;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MapRange {
     base_addr: usize,
     base_size: usize,
@@ -31,6 +33,7 @@ pub struct MapRange {
     read: bool,
     write: bool,
     exec: bool,
+    shared: bool,
 }
 
 impl MapRangeImpl for MapRange {
@@ -52,6 +55,9 @@ impl MapRangeImpl for MapRange {
     fn is_read(&self) -> bool {
         self.read
     }
+    fn permissions(&self) -> Permissions {
+        Permissions::new(self.read, self.write, self.exec, self.shared)
+    }
 }
 
 pub fn get_process_maps(pid: Pid) -> io::Result<Vec<MapRange>> {
@@ -67,6 +73,7 @@ pub fn get_process_maps(pid: Pid) -> io::Result<Vec<MapRange>> {
             read: page_range.read,
             write: page_range.write,
             exec: page_range.exec,
+            shared: page_range.shared,
         });
     }
     Ok(maps)
@@ -150,6 +157,7 @@ struct PageRange {
     read: bool,
     write: bool,
     exec: bool,
+    shared: bool,
 }
 
 /// Uses `VirtualQueryEx` to get info on *every* memory page range in the process.
@@ -201,6 +209,9 @@ fn get_process_page_ranges(pid: Pid) -> io::Result<Vec<PageRange>> {
                             | PAGE_EXECUTE_READWRITE
                             | PAGE_EXECUTE_WRITECOPY)
                         != 0,
+                    // Views of a section are shared unless they were mapped copy-on-write
+                    shared: meminfo.Type & MEM_MAPPED != 0
+                        && meminfo.Protect & (PAGE_WRITECOPY | PAGE_EXECUTE_WRITECOPY) == 0,
                 });
             }
 